@@ -1,35 +1,52 @@
 use base64::{Engine, engine::general_purpose::URL_SAFE, write::EncoderStringWriter};
 use eframe::Storage;
 use egui::Layout;
-use std::{cell::RefCell, ops::Deref, rc::Rc, sync::Arc};
+use serde::de::DeserializeOwned;
+use std::{cell::RefCell, collections::HashSet, ops::Deref, rc::Rc, sync::Arc};
 
 use crate::{
-    dbc::{Dbc, SerializableDbc},
-    messages::Messages,
+    dbc::{Dbc, Dbcs, SerializableDbc},
+    dbc_editor::DbcEditor,
+    heatmap::BitHeatmap,
+    import,
+    messages::{Bus, Messages},
+    overview::Overview,
     plots::Plots,
+    raw::RawSignal,
+    series::SeriesCache,
+    trace::Trace,
     widgets::close_button_ui,
 };
 
 #[derive(Default)]
 pub struct AppSaveState {
-    dbc: Option<SerializableDbc>,
+    dbcs: Vec<SerializableDbc>,
     plots: Plots,
     messages: Messages,
     ws_host: String,
+    load_errors: Vec<String>,
 }
 
 impl AppSaveState {
-    const DBC: &str = "DBC";
+    // Changes whenever the layout of anything saved changes, bincode can not read other layouts
+    const VERSION: &str = "VERSION";
+    const CURRENT_VERSION: &str = "2";
+    const DBCS: &str = "DBCS";
     const PLOTS: &str = "PLOTS";
     const MESSAGES: &str = "MESSAGES";
     const WS: &str = "WS";
 
     fn save(self, storage: &mut dyn Storage) {
+        storage.set_string(
+            AppSaveState::VERSION,
+            AppSaveState::CURRENT_VERSION.to_owned(),
+        );
+
         let mut writer = EncoderStringWriter::new(&URL_SAFE);
-        bincode::serde::encode_into_std_write(&self.dbc, &mut writer, bincode::config::standard())
+        bincode::serde::encode_into_std_write(&self.dbcs, &mut writer, bincode::config::standard())
             .unwrap();
         // TODO: quitar este unwrap, es solo para que me avise al hacer pruebas
-        storage.set_string(AppSaveState::DBC, writer.into_inner());
+        storage.set_string(AppSaveState::DBCS, writer.into_inner());
 
         let mut writer = EncoderStringWriter::new(&URL_SAFE);
         bincode::serde::encode_into_std_write(
@@ -62,60 +79,75 @@ impl AppSaveState {
         storage.set_string(AppSaveState::WS, writer.into_inner());
     }
 
+    // What could not be loaded is left out and reported
     fn load(storage: &dyn Storage) -> AppSaveState {
-        let Some(b64_raw) = storage.get_string(AppSaveState::DBC) else {
-            return Default::default();
-        };
-        let Ok(raw) = URL_SAFE.decode(&b64_raw) else {
-            return Default::default();
-        };
-        let dbc = bincode::serde::decode_from_slice(&raw, bincode::config::standard())
-            .map(|val| val.0)
-            .unwrap_or_default();
-
-        let Some(b64_raw) = storage.get_string(AppSaveState::MESSAGES) else {
-            return Default::default();
-        };
-        let Ok(raw) = URL_SAFE.decode(&b64_raw) else {
-            return Default::default();
-        };
-        let messages = bincode::serde::decode_from_slice(&raw, bincode::config::standard())
-            .map(|val| val.0)
-            .unwrap_or_default();
-
-        let Some(b64_raw) = storage.get_string(AppSaveState::PLOTS) else {
-            return Default::default();
-        };
-        let Ok(raw) = URL_SAFE.decode(&b64_raw) else {
-            return Default::default();
-        };
-        let plots = bincode::serde::decode_from_slice(&raw, bincode::config::standard())
-            .map(|val| val.0)
-            .unwrap_or_default();
-
-        let Some(b64_raw) = storage.get_string(AppSaveState::WS) else {
-            return Default::default();
-        };
-        let Ok(raw) = URL_SAFE.decode(&b64_raw) else {
-            return Default::default();
-        };
-        let ws_host = bincode::serde::decode_from_slice(&raw, bincode::config::standard())
-            .map(|val| val.0)
-            .unwrap_or_default();
+        let mut load_errors = Vec::new();
+        let version = storage.get_string(AppSaveState::VERSION);
+        if version.as_deref() != Some(AppSaveState::CURRENT_VERSION) {
+            // A first start has nothing saved
+            if version.is_some() || storage.get_string(AppSaveState::DBCS).is_some() {
+                load_errors.push(String::from(
+                    "The saved session was made by another version and could not be loaded",
+                ));
+            }
+            return AppSaveState {
+                load_errors,
+                ..Default::default()
+            };
+        }
 
         AppSaveState {
-            dbc,
-            plots,
-            messages,
-            ws_host,
+            dbcs: load_value(storage, AppSaveState::DBCS, "dbcs", &mut load_errors),
+            plots: load_value(storage, AppSaveState::PLOTS, "plots", &mut load_errors),
+            messages: load_value(storage, AppSaveState::MESSAGES, "log", &mut load_errors),
+            ws_host: load_value(
+                storage,
+                AppSaveState::WS,
+                "server address",
+                &mut load_errors,
+            ),
+            load_errors,
         }
     }
 }
 
+fn load_value<T: DeserializeOwned + Default>(
+    storage: &dyn Storage,
+    key: &str,
+    what: &str,
+    load_errors: &mut Vec<String>,
+) -> T {
+    let Some(b64_raw) = storage.get_string(key) else {
+        return T::default();
+    };
+    let value = URL_SAFE
+        .decode(&b64_raw)
+        .map_err(|error| error.to_string())
+        .and_then(|raw| {
+            bincode::serde::decode_from_slice(&raw, bincode::config::standard())
+                .map(|val| val.0)
+                .map_err(|error| error.to_string())
+        });
+    value.unwrap_or_else(|error| {
+        load_errors.push(format!("The saved {what} could not be loaded: {error}"));
+        T::default()
+    })
+}
+
 pub struct App {
-    pub dbc: Option<Dbc>,
+    pub dbcs: Dbcs,
     pub messages: Messages,
+    // Frames of these buses are not plotted
+    pub hidden_buses: HashSet<Bus>,
     pub plots: Plots,
+    // Raw signal being defined in the side panel
+    pub raw_signal: RawSignal,
+    // Decoded values of the plotted signals
+    pub series: SeriesCache,
+    pub trace: Trace,
+    pub overview: Overview,
+    pub heatmap: BitHeatmap,
+    pub dbc_editor: DbcEditor,
     pub ws_addr: String,
 
     pub ws_connected: bool,
@@ -125,9 +157,16 @@ pub struct App {
 impl Default for App {
     fn default() -> Self {
         Self {
-            dbc: None,
+            dbcs: Dbcs::default(),
             messages: Messages::empty(),
+            hidden_buses: HashSet::new(),
             plots: Plots::default(),
+            raw_signal: RawSignal::default(),
+            series: SeriesCache::default(),
+            trace: Trace::default(),
+            overview: Overview::default(),
+            heatmap: BitHeatmap::default(),
+            dbc_editor: DbcEditor::default(),
             ws_addr: String::from("ws://localhost:3333"),
             ws_connected: false,
             errors: Vec::new(),
@@ -146,32 +185,80 @@ impl App {
     }
 
     pub fn handle_dbc(&mut self, name: String, bytes: Arc<[u8]>) {
+        self.series.invalidate_dbc(&name);
         match Dbc::new(Arc::from(name), bytes) {
             Ok(dbc) => {
-                let _ = self.dbc.insert(dbc);
+                self.errors.extend(dbc.warning.clone());
+                self.dbcs.insert(dbc);
             }
             Err(e) => self.errors.push(e),
         }
     }
 
+    // Dbcs, and the other network descriptions converted to dbc
+    pub fn handle_network_file(&mut self, name: String, bytes: Arc<[u8]>) {
+        if name.to_lowercase().ends_with(".dbc") {
+            self.handle_dbc(name, bytes);
+            return;
+        }
+        match import::convert(&name, &bytes) {
+            Ok((converted, warnings)) => {
+                for dbc in converted {
+                    // Bound to its bus when the log has one with that name
+                    let bus = dbc
+                        .bus
+                        .as_deref()
+                        .and_then(Bus::new)
+                        .filter(|bus| self.messages.buses.contains(bus));
+                    self.handle_dbc(dbc.name.clone(), Arc::from(dbc.text.into_bytes()));
+                    if let Some(bus) = bus
+                        && let Some(loaded) = self
+                            .dbcs
+                            .0
+                            .iter_mut()
+                            .find(|loaded| *loaded.name == dbc.name)
+                    {
+                        loaded.bus = Some(bus);
+                    }
+                }
+                self.errors.extend(warnings);
+            }
+            Err(error) => self.errors.push(error),
+        }
+    }
+
     fn get_save_state(&self) -> AppSaveState {
         AppSaveState {
-            dbc: self.dbc.as_ref().map(|dbc| dbc.into_serializable()),
+            dbcs: self
+                .dbcs
+                .0
+                .iter()
+                .map(|dbc| dbc.into_serializable())
+                .collect(),
             plots: self.plots.clone(),
             messages: self.messages.clone(),
             ws_host: self.ws_addr.clone(),
+            load_errors: Vec::new(),
         }
     }
 
     fn from_save_state(save_state: AppSaveState) -> Self {
+        let mut errors = save_state.load_errors;
+        let dbcs = save_state
+            .dbcs
+            .into_iter()
+            .filter_map(|saved_dbc| {
+                Dbc::from_serializable(saved_dbc)
+                    .map_err(|error| errors.push(error))
+                    .ok()
+            })
+            .collect();
         Self {
-            dbc: save_state
-                .dbc
-                .map(|saved_dbc| Dbc::from_serializable(saved_dbc))
-                .and_then(|dbc| dbc.ok()),
+            dbcs: Dbcs(dbcs),
             plots: save_state.plots,
             messages: save_state.messages,
             ws_addr: save_state.ws_host,
+            errors,
             ..Default::default()
         }
     }
@@ -185,8 +272,11 @@ impl App {
                     .clone()
                     .expect("Field is guranteed to be set by the backend");
 
-                if file_name.ends_with(".dbc") {
-                    self.handle_dbc(file.name.clone(), bytes);
+                let network_file = import::EXTENSIONS
+                    .iter()
+                    .any(|extension| file_name.ends_with(&format!(".{extension}")));
+                if file_name.ends_with(".dbc") || network_file {
+                    self.handle_network_file(file.name.clone(), bytes);
                 } else if file_name.ends_with(".log") {
                     let file_contents = String::from_utf8_lossy(&bytes);
 
@@ -226,6 +316,21 @@ impl eframe::App for SharedApp {
                 if ui.button("Add Plot").clicked() {
                     app.plots.add_one();
                 }
+                ui.toggle_value(&mut app.trace.open, "Trace")
+                    .on_hover_text("Every frame of the log");
+                ui.toggle_value(&mut app.overview.open, "Overview")
+                    .on_hover_text("Every id of the log with its period");
+                ui.toggle_value(&mut app.heatmap.open, "Bit changes")
+                    .on_hover_text("How often every bit of an id changes");
+                ui.toggle_value(&mut app.dbc_editor.open, "DBC editor")
+                    .on_hover_text("Edit the messages and signals of a dbc and export it");
+                ui.separator();
+                app.plots.follow_ui(ui);
+                ui.separator();
+                let app = &mut *app;
+                app.plots.cursors_ui(ui, &app.messages);
+                ui.separator();
+                app.plots.time_origin_ui(ui, &app.messages);
             });
         });
 
@@ -255,6 +360,45 @@ impl eframe::App for SharedApp {
 
         app.draw_side_panel(&ctx, self.clone());
 
+        if app.trace.open {
+            let app = &mut *app;
+            app.trace
+                .window(ctx, &app.messages, &app.dbcs, &mut app.plots);
+        }
+        if app.overview.open {
+            let app = &mut *app;
+            app.overview.window(ctx, &app.messages, &app.dbcs);
+        }
+        if app.heatmap.open {
+            let app = &mut *app;
+            app.heatmap.window(
+                ctx,
+                &app.messages,
+                &app.hidden_buses,
+                &app.plots,
+                &mut app.raw_signal,
+            );
+        }
+        if app.dbc_editor.open {
+            let app = &mut *app;
+            if let Some(update) = app.dbc_editor.window(ctx, &app.dbcs, &app.raw_signal) {
+                app.plots.dbc_edited(&update.name, &update.moved);
+                if let Some((copy, moved)) = &update.replaced_copy {
+                    app.plots.dbc_edited(copy, moved);
+                    app.plots.dbc_renamed(copy, &update.name);
+                    app.dbcs.0.retain(|dbc| dbc.name != *copy);
+                    app.series.invalidate_dbc(copy);
+                }
+                app.handle_dbc(update.name.to_string(), Arc::from(update.text.into_bytes()));
+            }
+        }
+
+        if app.plots.has_cursors() {
+            egui::TopBottomPanel::bottom("cursor_readout")
+                .resizable(true)
+                .show(ctx, |ui| Plots::cursor_readout_ui(&mut app, ui));
+        }
+
         egui::CentralPanel::default().show(&ctx, |ui| {
             Plots::draw(&mut app, ui);
         });
@@ -1,8 +1,8 @@
 use can_dbc::{DBC, Message};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
-use crate::messages::RawCanMessageId;
+use crate::messages::{Bus, RawCanMessageId};
 
 fn generate_map_from_dbc(dbc: &DBC) -> HashMap<RawCanMessageId, Message> {
     dbc.messages()
@@ -11,57 +11,340 @@ fn generate_map_from_dbc(dbc: &DBC) -> HashMap<RawCanMessageId, Message> {
         .collect()
 }
 
+// Earlier dbcs take priority when several of them define the same message on the same bus
+#[derive(Default)]
+pub struct Dbcs(pub Vec<Dbc>);
+
+impl Dbcs {
+    pub fn get(&self, name: &str) -> Option<&Dbc> {
+        self.0.iter().find(|dbc| &*dbc.name == name)
+    }
+
+    // Replaces the dbc with the same name if it was already loaded, keeping its bus
+    pub fn insert(&mut self, mut dbc: Dbc) {
+        if let Some(loaded) = self.0.iter_mut().find(|loaded| loaded.name == dbc.name) {
+            dbc.bus = loaded.bus;
+            *loaded = dbc;
+        } else {
+            self.0.push(dbc);
+        }
+    }
+
+    // Dbc whose definition is used for the frames of that id on that bus
+    pub fn message_owner(&self, id: RawCanMessageId, bus: Bus) -> Option<&Dbc> {
+        self.0
+            .iter()
+            .find(|dbc| dbc.decodes_bus(bus) && dbc.messages_map.contains_key(&id))
+    }
+
+    // Signal by message and signal name. Names do not say which dbc they mean, so they have to be
+    // in only one of them
+    pub fn find_signal(&self, message_name: &str, signal_name: &str) -> Result<Signal, String> {
+        let mut found = self.0.iter().filter_map(|dbc| {
+            let (message_id, message) = dbc
+                .messages_map
+                .iter()
+                .find(|(_, message)| message.message_name() == message_name)?;
+            let signal_idx = message
+                .signals()
+                .iter()
+                .position(|signal| signal.name() == signal_name)?;
+            Some(Signal {
+                dbc: dbc.name.clone(),
+                message_id: *message_id,
+                signal_idx,
+            })
+        });
+        let signal = found
+            .next()
+            .ok_or_else(|| format!("Unknown signal {message_name}.{signal_name}"))?;
+        let others: Vec<Arc<str>> = found.map(|other| other.dbc).collect();
+        if !others.is_empty() {
+            return Err(format!(
+                "{message_name}.{signal_name} is in more than one dbc: {}, {}",
+                signal.dbc,
+                others.join(", ")
+            ));
+        }
+        Ok(signal)
+    }
+
+    pub fn conflicts(&self) -> Vec<DbcConflict> {
+        let mut conflicts = Vec::new();
+        for (idx, dbc) in self.0.iter().enumerate() {
+            for shadowed in &self.0[idx + 1..] {
+                let buses_overlap = match (dbc.bus, shadowed.bus) {
+                    (Some(bus), Some(other_bus)) => bus == other_bus,
+                    _ => true,
+                };
+                if !buses_overlap {
+                    continue;
+                }
+
+                for id in shadowed.messages_map.keys() {
+                    if dbc.messages_map.contains_key(id) {
+                        conflicts.push(DbcConflict {
+                            message_id: *id,
+                            used: dbc.name.clone(),
+                            shadowed: shadowed.name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        conflicts.sort_by_key(|conflict| conflict.message_id);
+        conflicts
+    }
+}
+
+pub struct DbcConflict {
+    pub message_id: RawCanMessageId,
+    pub used: Arc<str>,
+    pub shadowed: Arc<str>,
+}
+
 pub struct Dbc {
     pub name: Arc<str>,
+    // Only frames of this bus are decoded with this dbc, None means every bus
+    pub bus: Option<Bus>,
     pub messages_map: HashMap<RawCanMessageId, Message>,
     raw_dbc: Arc<[u8]>,
     pub inner: DBC,
+    // What could not be read when the dbc was only read partially
+    pub warning: Option<String>,
 }
 
 impl Dbc {
     pub fn new(name: Arc<str>, raw_dbc: Arc<[u8]>) -> Result<Self, String> {
-        match DBC::from_slice(&raw_dbc) {
-            Ok(dbc) => Ok(Self {
-                name: name,
-                messages_map: generate_map_from_dbc(&dbc),
-                raw_dbc: raw_dbc,
-                inner: dbc,
-            }),
-            Err(e) => match e {
-                can_dbc::Error::Incomplete(dbc, _str) => {
-                    log::warn!("INCOMPLETE DBC");
-                    Ok(Self {
-                        name: name,
-                        messages_map: generate_map_from_dbc(&dbc),
-                        raw_dbc: raw_dbc,
-                        inner: dbc,
-                    })
-                }
-                _e => Err(format!("INVALID DBC")),
-            },
-        }
+        let text = decode_text(&raw_dbc);
+        let (dbc, warning) = match DBC::try_from(&*text) {
+            Ok(dbc) => (dbc, None),
+            Err(can_dbc::Error::Incomplete(dbc, rest)) => {
+                let warning = incomplete_report(&name, &text, &dbc, rest);
+                log::warn!("{warning}");
+                (dbc, Some(warning))
+            }
+            Err(can_dbc::Error::Nom(nom::Err::Error(error) | nom::Err::Failure(error))) => {
+                let rest = error.input.trim_start();
+                let (line, column) = position(&text, rest);
+                return Err(format!(
+                    "{name} is not a valid dbc, it could not be read from line {line}, column {column}: {}",
+                    excerpt(rest)
+                ));
+            }
+            Err(error) => return Err(format!("{name} is not a valid dbc: {error:?}")),
+        };
+
+        Ok(Self {
+            name,
+            bus: None,
+            messages_map: generate_map_from_dbc(&dbc),
+            raw_dbc,
+            inner: dbc,
+            warning,
+        })
+    }
+
+    // Text the dbc was loaded from
+    pub fn text(&self) -> Cow<'_, str> {
+        decode_text(&self.raw_dbc)
+    }
+
+    pub fn decodes_bus(&self, bus: Bus) -> bool {
+        self.bus.is_none_or(|dbc_bus| dbc_bus == bus)
     }
 
     pub fn into_serializable(&self) -> SerializableDbc {
         SerializableDbc {
             name: self.name.clone(),
+            bus: self.bus,
             raw_dbc: self.raw_dbc.clone(),
         }
     }
 
     pub fn from_serializable(serializable: SerializableDbc) -> Result<Self, String> {
-        Self::new(serializable.name, serializable.raw_dbc)
+        let mut dbc = Self::new(serializable.name, serializable.raw_dbc)?;
+        dbc.bus = serializable.bus;
+        Ok(dbc)
+    }
+}
+
+// Files written by Windows tools are often Latin-1, where every byte is a character
+pub fn decode_text(bytes: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => Cow::Owned(bytes.iter().map(|byte| *byte as char).collect()),
     }
 }
 
+// Where reading stopped and which messages and signals were loaded or skipped because of it
+fn incomplete_report(name: &str, text: &str, dbc: &DBC, rest: &str) -> String {
+    let rest = rest.trim_start();
+    let (line, column) = position(text, rest);
+    let mut report = format!(
+        "{name} could only be read up to line {line}, column {column}: {}",
+        excerpt(rest)
+    );
+
+    let loaded: Vec<String> = dbc
+        .messages()
+        .iter()
+        .map(|message| {
+            format!(
+                "{} ({} signals)",
+                message.message_name(),
+                message.signals().len()
+            )
+        })
+        .collect();
+    report.push_str(&format!("\nLoaded messages: {}", name_list(&loaded)));
+
+    let (messages, signals) = skipped(text, rest);
+    if !messages.is_empty() {
+        report.push_str(&format!("\nSkipped messages: {}", name_list(&messages)));
+    }
+    if !signals.is_empty() {
+        report.push_str(&format!("\nSkipped signals: {}", name_list(&signals)));
+    }
+    report
+}
+
+// Messages and signals of loaded messages defined in the part of the text that was not read
+fn skipped(text: &str, rest: &str) -> (Vec<String>, Vec<String>) {
+    let read = &text[..text.len() - rest.len()];
+    // Signals at the start of the rest belong to the last message that was read
+    let message = read
+        .lines()
+        .rev()
+        .find_map(message_name)
+        .unwrap_or_default();
+    let mut message_loaded = true;
+    let mut messages = Vec::new();
+    let mut signals = Vec::new();
+    for line in rest.lines() {
+        if let Some(name) = message_name(line) {
+            messages.push(name.to_string());
+            message_loaded = false;
+        } else if let Some(signal) = line.trim_start().strip_prefix("SG_ ")
+            && message_loaded
+        {
+            let signal = signal.split_whitespace().next().unwrap_or_default();
+            signals.push(format!("{message}.{signal}"));
+        }
+    }
+    (messages, signals)
+}
+
+// Name of the message a BO_ line defines
+fn message_name(line: &str) -> Option<&str> {
+    let mut fields = line.trim_start().strip_prefix("BO_ ")?.split_whitespace();
+    fields.next()?;
+    Some(fields.next()?.trim_end_matches(':'))
+}
+
+// Line and column, starting at 1, where the rest starts in the text
+fn position(text: &str, rest: &str) -> (usize, usize) {
+    let read = &text[..text.len() - rest.len()];
+    let line_start = read.rfind('\n').map_or(0, |idx| idx + 1);
+    (
+        read.matches('\n').count() + 1,
+        read[line_start..].chars().count() + 1,
+    )
+}
+
+// Start of the line where reading stopped
+fn excerpt(rest: &str) -> String {
+    let line = rest.lines().next().unwrap_or_default();
+    match line.char_indices().nth(60) {
+        Some((end, _)) => format!("\"{}…\"", &line[..end]),
+        None => format!("\"{line}\""),
+    }
+}
+
+// Long lists only show their start
+fn name_list(names: &[String]) -> String {
+    const SHOWN: usize = 20;
+    if names.is_empty() {
+        return String::from("none");
+    }
+    let mut list = names[..names.len().min(SHOWN)].join(", ");
+    if names.len() > SHOWN {
+        list.push_str(&format!(" and {} more", names.len() - SHOWN));
+    }
+    list
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SerializableDbc {
     name: Arc<str>,
+    bus: Option<Bus>,
     raw_dbc: Arc<[u8]>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
 pub struct Signal {
+    // Name of the dbc the signal comes from
+    pub dbc: Arc<str>,
     pub message_id: RawCanMessageId,
     pub signal_idx: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_diagnostics() {
+        let text = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\n\
+            BO_ 256 Engine: 8 ECU\n\
+            \x20SG_ Speed : 0|16@1+ (1,0) [0|65535] \"rpm\" Vector__XXX\n\
+            \x20SG_ Temp : 16|8@1+ (1,-40) [-40|215] \"°C\" Vector__XXX\n\
+            \x20SG_ Bad : 24|8@1+ (1,0) [0|255] oops Vector__XXX\n\
+            \x20SG_ Lost : 32|8@1+ (1,0) [0|255] \"\" Vector__XXX\n\n\
+            BO_ 512 Brakes: 8 ECU\n\
+            \x20SG_ Pressure : 0|8@1+ (1,0) [0|255] \"bar\" Vector__XXX\n";
+        let dbc = Dbc::new(Arc::from("car.dbc"), Arc::from(text.as_bytes())).unwrap();
+        assert_eq!(
+            dbc.warning.unwrap(),
+            "car.dbc could only be read up to line 12, column 2: \
+            \"SG_ Bad : 24|8@1+ (1,0) [0|255] oops Vector__XXX\"\n\
+            Loaded messages: Engine (2 signals)\n\
+            Skipped messages: Brakes\n\
+            Skipped signals: Engine.Bad, Engine.Lost"
+        );
+
+        // Latin-1 text is read too
+        let latin1: Vec<u8> = text.chars().map(|char| char as u8).collect();
+        let dbc = Dbc::new(Arc::from("car.dbc"), Arc::from(latin1)).unwrap();
+        assert_eq!(dbc.inner.messages()[0].signals()[1].unit(), "°C");
+
+        let error = Dbc::new(Arc::from("notes.txt"), Arc::from(&b"\n  hello"[..]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "notes.txt is not a valid dbc, it could not be read from line 2, column 3: \"hello\""
+        );
+    }
+
+    #[test]
+    fn signals_in_several_dbcs_are_ambiguous() {
+        let text = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\n\
+            BO_ 256 Engine: 8 ECU\n\
+            \x20SG_ Speed : 0|16@1+ (1,0) [0|65535] \"rpm\" Vector__XXX\n";
+        let mut dbcs = Dbcs::default();
+        dbcs.insert(Dbc::new(Arc::from("a.dbc"), Arc::from(text.as_bytes())).unwrap());
+        assert_eq!(dbcs.find_signal("Engine", "Speed").unwrap().signal_idx, 0);
+        assert_eq!(
+            dbcs.find_signal("Engine", "Torque").unwrap_err(),
+            "Unknown signal Engine.Torque"
+        );
+
+        dbcs.insert(Dbc::new(Arc::from("b.dbc"), Arc::from(text.as_bytes())).unwrap());
+        assert_eq!(
+            dbcs.find_signal("Engine", "Speed").unwrap_err(),
+            "Engine.Speed is in more than one dbc: a.dbc, b.dbc"
+        );
+    }
+}
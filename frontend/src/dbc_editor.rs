@@ -0,0 +1,1134 @@
+use can_dbc::{ByteOrder, MultiplexIndicator, SignalExtendedValueType, Transmitter, ValueType};
+use egui::{DragValue, TextEdit, Ui};
+use rfd::AsyncFileDialog;
+use std::{collections::HashMap, fmt::Write, sync::Arc, time::Duration};
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    dbc::{Dbc, Dbcs},
+    messages::RawCanMessageId,
+    raw::RawSignal,
+};
+
+// Message and signal position of a signal in a dbc, like in dbc::Signal
+pub type SignalPosition = (RawCanMessageId, usize);
+
+// Messages and signals of a dbc, edited in a window and loaded again on every change. Loaded dbcs
+// are edited in a copy until the user replaces the original with it
+#[derive(Default)]
+pub struct DbcEditor {
+    pub open: bool,
+    draft: Option<DbcDraft>,
+    // Dbc the draft is a copy of, and where the signals of the original are in the last loaded
+    // version of the copy
+    original: Option<(Arc<str>, HashMap<SignalPosition, SignalPosition>)>,
+    // Set by the replace button
+    replace: bool,
+    // Message shown on the right and signal whose value table is shown
+    message: usize,
+    signal: Option<usize>,
+    // Text the dbc was last loaded from, so it is only loaded again after a change
+    applied: String,
+    // Text of the draft being edited and when it last changed, it is loaded once it settles
+    changed: Option<(String, f64)>,
+}
+
+// Seconds a change has to stay as it is before the dbc is loaded again, so that dragging a value
+// or typing a name does not decode everything again on every frame
+const APPLY_DELAY: f64 = 0.4;
+
+// New text of the edited dbc and where the signals of its last version ended up, the ones missing
+// were removed
+pub struct DbcUpdate {
+    pub name: Arc<str>,
+    pub text: String,
+    pub moved: HashMap<SignalPosition, SignalPosition>,
+    // When the edits replace the original, the copy they were made in, which goes away, and where
+    // its signals are in the original now
+    pub replaced_copy: Option<(Arc<str>, HashMap<SignalPosition, SignalPosition>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbcDraft {
+    pub name: Arc<str>,
+    pub nodes: Vec<String>,
+    pub messages: Vec<MessageDraft>,
+    // Statements the editor does not show, like comments and attributes, written back as they were
+    pub kept: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageDraft {
+    pub id: RawCanMessageId,
+    pub name: String,
+    // Bytes
+    pub size: u64,
+    // Empty when no node sends it
+    pub transmitter: String,
+    pub signals: Vec<SignalDraft>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalDraft {
+    // Where the signal was in the last loaded version of the dbc, None for new signals
+    pub origin: Option<SignalPosition>,
+    pub name: String,
+    pub multiplexer: MultiplexIndicator,
+    pub start_bit: u64,
+    pub size: u64,
+    pub byte_order: ByteOrder,
+    pub value_type: ValueType,
+    // IEEE float and double signals go in SIG_VALTYPE_
+    pub float: SignalExtendedValueType,
+    pub factor: f64,
+    pub offset: f64,
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
+    pub receivers: Vec<String>,
+    // Names of raw values, written as VAL_
+    pub values: Vec<(f64, String)>,
+}
+
+impl SignalDraft {
+    pub fn new(name: String) -> Self {
+        Self {
+            origin: None,
+            name,
+            multiplexer: MultiplexIndicator::Plain,
+            start_bit: 0,
+            size: 8,
+            byte_order: ByteOrder::LittleEndian,
+            value_type: ValueType::Unsigned,
+            float: SignalExtendedValueType::SignedOrUnsignedInteger,
+            factor: 1.,
+            offset: 0.,
+            min: 0.,
+            max: 255.,
+            unit: String::new(),
+            receivers: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    // Highest bit of the frame the signal uses, numbered like the start bit of little endian signals
+    pub fn last_bit(&self) -> u64 {
+        match self.byte_order {
+            ByteOrder::LittleEndian => self.start_bit + self.size.max(1) - 1,
+            // Big endian signals go from the most significant bit down and on into the next byte
+            ByteOrder::BigEndian => {
+                let mut bit = self.start_bit;
+                let mut last = bit;
+                for _ in 1..self.size {
+                    bit = if bit.is_multiple_of(8) {
+                        bit + 15
+                    } else {
+                        bit - 1
+                    };
+                    last = last.max(bit);
+                }
+                last
+            }
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match (self.float, self.value_type) {
+            (SignalExtendedValueType::IEEEfloat32Bit, _) => "Float",
+            (SignalExtendedValueType::IEEEdouble64bit, _) => "Double",
+            (_, ValueType::Signed) => "Signed",
+            (_, ValueType::Unsigned) => "Unsigned",
+        }
+    }
+}
+
+const SIGNAL_TYPES: [(&str, ValueType, SignalExtendedValueType); 4] = [
+    (
+        "Unsigned",
+        ValueType::Unsigned,
+        SignalExtendedValueType::SignedOrUnsignedInteger,
+    ),
+    (
+        "Signed",
+        ValueType::Signed,
+        SignalExtendedValueType::SignedOrUnsignedInteger,
+    ),
+    (
+        "Float",
+        ValueType::Signed,
+        SignalExtendedValueType::IEEEfloat32Bit,
+    ),
+    (
+        "Double",
+        ValueType::Signed,
+        SignalExtendedValueType::IEEEdouble64bit,
+    ),
+];
+
+// Statements kept from the dbc the draft was loaded from, by where they go in the text. can_dbc
+// only reads every kind of statement in this order. Extended multiplexing is not kept, dbcs with
+// it can not be edited because the editor does not know how it depends on the signals
+const KEPT_BEFORE_MESSAGES: [&str; 1] = ["VAL_TABLE_"];
+const KEPT_AFTER_MESSAGES: [&str; 8] = [
+    "BO_TX_BU_",
+    "EV_",
+    "ENVVAR_DATA_",
+    "SGTYPE_",
+    "CM_",
+    "BA_DEF_",
+    "BA_DEF_DEF_",
+    "BA_",
+];
+// Value tables of environment variables, the ones of signals are written from the draft
+const KEPT_WITH_VALUES: [&str; 1] = ["VAL_"];
+const KEPT_AFTER_VALUES: [&str; 2] = ["SIG_TYPE_REF_", "SIG_GROUP_"];
+
+// Statements of the text that the draft does not hold, in the order they are in
+fn kept_statements(text: &str) -> Vec<String> {
+    let kept_keywords = || {
+        KEPT_BEFORE_MESSAGES
+            .iter()
+            .chain(&KEPT_AFTER_MESSAGES)
+            .chain(&KEPT_AFTER_VALUES)
+    };
+    let mut kept = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|char: char| !char.is_whitespace()) {
+        rest = &rest[start..];
+        let mut words = rest.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+        let environment_values = keyword == "VAL_"
+            && words
+                .next()
+                .is_some_and(|word| word.parse::<u32>().is_err());
+        let end = if kept_keywords().any(|kept| *kept == keyword) || environment_values {
+            let end = statement_end(rest);
+            kept.push(rest[..end].to_owned());
+            end
+        } else if keyword == "NS_" {
+            // Followed by indented keywords without a ;
+            rest.match_indices('\n')
+                .map(|(idx, _)| idx + 1)
+                .find(|idx| rest[*idx..].starts_with(|char: char| !char.is_whitespace()))
+                .unwrap_or(rest.len())
+        } else {
+            rest.find('\n').unwrap_or(rest.len())
+        };
+        rest = &rest[end..];
+    }
+    kept
+}
+
+// Up to the ; that ends the statement, outside of the strings
+fn statement_end(text: &str) -> usize {
+    let mut quoted = false;
+    for (idx, char) in text.char_indices() {
+        match char {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return idx + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+impl DbcDraft {
+    pub fn new(name: Arc<str>) -> Self {
+        Self {
+            name,
+            nodes: Vec::new(),
+            messages: Vec::new(),
+            kept: Vec::new(),
+        }
+    }
+
+    fn from_dbc(dbc: &Dbc) -> Self {
+        let inner = &dbc.inner;
+        let messages = inner
+            .messages()
+            .iter()
+            .map(|message| {
+                let id = RawCanMessageId::from(*message.message_id());
+                let signals = message
+                    .signals()
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, signal)| SignalDraft {
+                        origin: Some((id, idx)),
+                        name: signal.name().clone(),
+                        multiplexer: *signal.multiplexer_indicator(),
+                        start_bit: *signal.start_bit(),
+                        size: *signal.signal_size(),
+                        byte_order: *signal.byte_order(),
+                        value_type: *signal.value_type(),
+                        float: inner
+                            .extended_value_type_for_signal(*message.message_id(), signal.name())
+                            .copied()
+                            .unwrap_or(SignalExtendedValueType::SignedOrUnsignedInteger),
+                        factor: *signal.factor(),
+                        offset: *signal.offset(),
+                        min: *signal.min(),
+                        max: *signal.max(),
+                        unit: signal.unit().clone(),
+                        receivers: signal
+                            .receivers()
+                            .iter()
+                            .filter(|receiver| *receiver != "Vector__XXX")
+                            .cloned()
+                            .collect(),
+                        values: inner
+                            .value_descriptions_for_signal(*message.message_id(), signal.name())
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|description| (*description.a(), description.b().clone()))
+                            .collect(),
+                    })
+                    .collect();
+                MessageDraft {
+                    id,
+                    name: message.message_name().clone(),
+                    size: *message.message_size(),
+                    transmitter: match message.transmitter() {
+                        Transmitter::NodeName(node) => node.clone(),
+                        Transmitter::VectorXXX => String::new(),
+                    },
+                    signals,
+                }
+            })
+            .collect();
+
+        Self {
+            name: dbc.name.clone(),
+            nodes: inner
+                .nodes()
+                .iter()
+                .flat_map(|node| node.0.clone())
+                .collect(),
+            messages,
+            kept: kept_statements(&dbc.text()),
+        }
+    }
+
+    // Kept statements of these kinds, each kind together
+    fn write_kept(&self, text: &mut String, keywords: &[&str]) {
+        for keyword in keywords {
+            for statement in &self.kept {
+                if statement.split_whitespace().next() == Some(keyword) {
+                    text.push_str(statement);
+                    text.push('\n');
+                }
+            }
+        }
+    }
+
+    // Adds the raw signal to the message of its id, adding the message if there is none
+    fn add_raw_signal(&mut self, raw: &RawSignal) -> (usize, usize) {
+        let message_idx = match self
+            .messages
+            .iter()
+            .position(|message| message.id == raw.id)
+        {
+            Some(idx) => idx,
+            None => {
+                self.messages.push(MessageDraft {
+                    id: raw.id,
+                    name: format!("Message_{:X}", raw.id.id),
+                    size: 8,
+                    transmitter: String::new(),
+                    signals: Vec::new(),
+                });
+                self.messages.len() - 1
+            }
+        };
+
+        let message = &mut self.messages[message_idx];
+        let mut name = format!("Signal_{}", raw.start_bit);
+        let mut suffix = 1;
+        while message.signals.iter().any(|signal| signal.name == name) {
+            suffix += 1;
+            name = format!("Signal_{}_{suffix}", raw.start_bit);
+        }
+        let max = if raw.signed {
+            (1u64 << (raw.size - 1)) as f64 - 1.
+        } else {
+            (u64::MAX >> (64 - raw.size)) as f64
+        };
+        let signal = SignalDraft {
+            start_bit: raw.start_bit as u64,
+            size: raw.size as u64,
+            byte_order: if raw.big_endian {
+                ByteOrder::BigEndian
+            } else {
+                ByteOrder::LittleEndian
+            },
+            value_type: if raw.signed {
+                ValueType::Signed
+            } else {
+                ValueType::Unsigned
+            },
+            min: if raw.signed { -max - 1. } else { 0. },
+            max,
+            ..SignalDraft::new(name)
+        };
+        message.size = message.size.max(signal.last_bit() / 8 + 1);
+        message.signals.push(signal);
+        (message_idx, message.signals.len() - 1)
+    }
+
+    // Everything that would make the text an invalid dbc or decode differently than shown
+    pub fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (idx, message) in self.messages.iter().enumerate() {
+            if !is_identifier(&message.name) {
+                errors.push(format!("{} is not a valid message name", message.name));
+            }
+            if !message.transmitter.is_empty() && !is_identifier(&message.transmitter) {
+                errors.push(format!(
+                    "{}: {} is not a valid node name",
+                    message.name, message.transmitter
+                ));
+            }
+            let max_id = if message.id.extended {
+                0x1FFFFFFF
+            } else {
+                0x7FF
+            };
+            if message.id.id > max_id {
+                errors.push(format!("{}: the id is too large", message.name));
+            }
+            for other in &self.messages[idx + 1..] {
+                if other.id == message.id {
+                    errors.push(format!(
+                        "{} and {} have the same id",
+                        message.name, other.name
+                    ));
+                }
+                if other.name == message.name {
+                    errors.push(format!("There are two messages called {}", message.name));
+                }
+            }
+
+            let has_multiplexor = message.signals.iter().any(|signal| {
+                matches!(
+                    signal.multiplexer,
+                    MultiplexIndicator::Multiplexor
+                        | MultiplexIndicator::MultiplexorAndMultiplexedSignal(_)
+                )
+            });
+            for (signal_idx, signal) in message.signals.iter().enumerate() {
+                let name = format!("{}.{}", message.name, signal.name);
+                if !is_identifier(&signal.name) {
+                    errors.push(format!("{name} is not a valid signal name"));
+                }
+                if message.signals[signal_idx + 1..]
+                    .iter()
+                    .any(|other| other.name == signal.name)
+                {
+                    errors.push(format!("There are two signals called {name}"));
+                }
+                if signal.size == 0 || signal.size > 64 {
+                    errors.push(format!("{name} has to be 1 to 64 bits long"));
+                } else if signal.last_bit() >= message.size * 8 {
+                    errors.push(format!("{name} does not fit in the message"));
+                }
+                match signal.float {
+                    SignalExtendedValueType::IEEEfloat32Bit if signal.size != 32 => {
+                        errors.push(format!("{name} is a float, it has to be 32 bits long"))
+                    }
+                    SignalExtendedValueType::IEEEdouble64bit if signal.size != 64 => {
+                        errors.push(format!("{name} is a double, it has to be 64 bits long"))
+                    }
+                    _ => {}
+                }
+                if signal.factor == 0. {
+                    errors.push(format!("{name} needs a factor other than 0"));
+                }
+                if matches!(
+                    signal.multiplexer,
+                    MultiplexIndicator::MultiplexedSignal(_)
+                        | MultiplexIndicator::MultiplexorAndMultiplexedSignal(_)
+                ) && !has_multiplexor
+                {
+                    errors.push(format!(
+                        "{name} is multiplexed but {} has no multiplexor",
+                        message.name
+                    ));
+                }
+                if signal.unit.contains('"')
+                    || signal.values.iter().any(|(_, value)| value.contains('"'))
+                {
+                    errors.push(format!("{name}: units and value names can not have quotes"));
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn to_dbc_text(&self) -> String {
+        // Every transmitter and receiver has to be a node
+        let mut nodes = self.nodes.clone();
+        for message in &self.messages {
+            let receivers = message.signals.iter().flat_map(|signal| &signal.receivers);
+            for node in std::iter::once(&message.transmitter).chain(receivers) {
+                if !node.is_empty() && !nodes.contains(node) {
+                    nodes.push(node.clone());
+                }
+            }
+        }
+
+        let mut text = String::from("VERSION \"\"\n\nNS_ :\n\nBS_:\n\n");
+        let _ = writeln!(text, "BU_: {}\n", nodes.join(" "));
+        self.write_kept(&mut text, &KEPT_BEFORE_MESSAGES);
+
+        for message in &self.messages {
+            let transmitter = match message.transmitter.as_str() {
+                "" => "Vector__XXX",
+                transmitter => transmitter,
+            };
+            let _ = writeln!(
+                text,
+                "BO_ {} {}: {} {transmitter}",
+                raw_id(message.id),
+                message.name,
+                message.size
+            );
+            for signal in &message.signals {
+                let multiplexer = match signal.multiplexer {
+                    MultiplexIndicator::Plain => String::new(),
+                    MultiplexIndicator::Multiplexor => String::from(" M"),
+                    MultiplexIndicator::MultiplexedSignal(value) => format!(" m{value}"),
+                    MultiplexIndicator::MultiplexorAndMultiplexedSignal(value) => {
+                        format!(" m{value}M")
+                    }
+                };
+                let receivers = match signal.receivers.is_empty() {
+                    true => String::from("Vector__XXX"),
+                    false => signal.receivers.join(","),
+                };
+                let _ = writeln!(
+                    text,
+                    " SG_ {}{multiplexer} : {}|{}@{}{} ({},{}) [{}|{}] \"{}\" {receivers}",
+                    signal.name,
+                    signal.start_bit,
+                    signal.size,
+                    match signal.byte_order {
+                        ByteOrder::LittleEndian => '1',
+                        ByteOrder::BigEndian => '0',
+                    },
+                    match signal.value_type {
+                        ValueType::Signed => '-',
+                        ValueType::Unsigned => '+',
+                    },
+                    signal.factor,
+                    signal.offset,
+                    signal.min,
+                    signal.max,
+                    signal.unit
+                );
+            }
+            text.push('\n');
+        }
+
+        self.write_kept(&mut text, &KEPT_AFTER_MESSAGES);
+        for message in &self.messages {
+            for signal in message.signals.iter().filter(|s| !s.values.is_empty()) {
+                let _ = write!(text, "VAL_ {} {}", raw_id(message.id), signal.name);
+                for (value, name) in &signal.values {
+                    let _ = write!(text, " {value} \"{name}\"");
+                }
+                text.push_str(" ;\n");
+            }
+        }
+        self.write_kept(&mut text, &KEPT_WITH_VALUES);
+        self.write_kept(&mut text, &KEPT_AFTER_VALUES);
+        for message in &self.messages {
+            for signal in &message.signals {
+                let value_type = match signal.float {
+                    SignalExtendedValueType::SignedOrUnsignedInteger => continue,
+                    SignalExtendedValueType::IEEEfloat32Bit => 1,
+                    SignalExtendedValueType::IEEEdouble64bit => 2,
+                };
+                let _ = writeln!(
+                    text,
+                    "SIG_VALTYPE_ {} {} : {value_type};",
+                    raw_id(message.id),
+                    signal.name
+                );
+            }
+        }
+        text
+    }
+
+    // Moves the signal origins to where the signals are now and returns the old and new positions
+    fn take_moves(&mut self) -> HashMap<SignalPosition, SignalPosition> {
+        let mut moved = HashMap::new();
+        for message in &mut self.messages {
+            for (idx, signal) in message.signals.iter_mut().enumerate() {
+                if let Some(origin) = signal.origin.replace((message.id, idx)) {
+                    moved.insert(origin, (message.id, idx));
+                }
+            }
+        }
+        moved
+    }
+}
+
+// Name of the copy a loaded dbc is edited in, not taken by another loaded dbc
+fn copy_name(name: &str, dbcs: &Dbcs) -> Arc<str> {
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, "dbc"));
+    let mut copy = format!("{stem} (edited).{extension}");
+    let mut suffix = 1;
+    while dbcs.get(&copy).is_some() {
+        suffix += 1;
+        copy = format!("{stem} (edited {suffix}).{extension}");
+    }
+    Arc::from(copy)
+}
+
+// Message id as written in a dbc, with the highest bit set for extended ids
+fn raw_id(id: RawCanMessageId) -> u32 {
+    if id.extended { id.id | 1 << 31 } else { id.id }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+impl DbcEditor {
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        dbcs: &Dbcs,
+        raw_signal: &RawSignal,
+    ) -> Option<DbcUpdate> {
+        let mut open = self.open;
+        egui::Window::new("DBC editor")
+            .open(&mut open)
+            .default_size([1000., 500.])
+            .show(ctx, |ui| self.ui(ui, dbcs, raw_signal));
+        self.open = open;
+
+        // Only valid drafts are loaded, the plots keep the last valid version meanwhile
+        let draft = self.draft.as_mut()?;
+        if !draft.errors().is_empty() {
+            return None;
+        }
+        let text = draft.to_dbc_text();
+        let replace = std::mem::take(&mut self.replace);
+        if text == self.applied && !replace {
+            self.changed = None;
+            return None;
+        }
+        if !replace {
+            let now = ctx.input(|input| input.time);
+            let since = match &self.changed {
+                Some((changed, time)) if *changed == text => now - time,
+                _ => {
+                    self.changed = Some((text.clone(), now));
+                    0.
+                }
+            };
+            // Releasing the pointer repaints anyway
+            if ctx.input(|input| input.pointer.any_down()) {
+                return None;
+            }
+            if since < APPLY_DELAY {
+                ctx.request_repaint_after(Duration::from_secs_f64(APPLY_DELAY - since));
+                return None;
+            }
+        }
+        self.changed = None;
+        self.applied = text.clone();
+        let moved = draft.take_moves();
+        let Some((original, positions)) = &mut self.original else {
+            return Some(DbcUpdate {
+                name: draft.name.clone(),
+                text,
+                moved,
+                replaced_copy: None,
+            });
+        };
+        *positions = positions
+            .iter()
+            .filter_map(|(from, to)| Some((*from, *moved.get(to)?)))
+            .collect();
+        if !replace {
+            return Some(DbcUpdate {
+                name: draft.name.clone(),
+                text,
+                moved,
+                replaced_copy: None,
+            });
+        }
+
+        // Further edits go into a new copy of the replaced dbc
+        let update = DbcUpdate {
+            name: original.clone(),
+            text,
+            moved: std::mem::take(positions),
+            replaced_copy: Some((draft.name.clone(), moved)),
+        };
+        *positions = draft.take_moves();
+        Some(update)
+    }
+
+    fn ui(&mut self, ui: &mut Ui, dbcs: &Dbcs, raw_signal: &RawSignal) {
+        let mut load = None;
+        let mut new = false;
+        ui.horizontal(|ui| {
+            let selected = self.draft.as_ref().map(|draft| &*draft.name);
+            egui::ComboBox::from_label("DBC")
+                .selected_text(selected.unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    for dbc in &dbcs.0 {
+                        let editing = selected == Some(&*dbc.name);
+                        let picked = ui
+                            .add_enabled(
+                                dbc.inner.extended_multiplex().is_empty(),
+                                egui::Button::selectable(editing, &*dbc.name),
+                            )
+                            .on_disabled_hover_text(
+                                "Extended multiplexing (SG_MUL_VAL_) can not be edited",
+                            )
+                            .clicked();
+                        if picked && !editing {
+                            load = Some(dbc);
+                        }
+                    }
+                });
+            new = ui.button("New DBC").clicked();
+        });
+        if let Some(dbc) = load {
+            let mut draft = DbcDraft::from_dbc(dbc);
+            draft.name = copy_name(&dbc.name, dbcs);
+            self.edit(draft);
+            // Every signal is where it is in the original until the copy is edited
+            if let Some(draft) = &mut self.draft {
+                self.original = Some((dbc.name.clone(), draft.take_moves()));
+            }
+        }
+        if new {
+            let mut name = String::from("new.dbc");
+            let mut suffix = 1;
+            while dbcs.get(&name).is_some() {
+                suffix += 1;
+                name = format!("new_{suffix}.dbc");
+            }
+            self.edit(DbcDraft::new(Arc::from(name)));
+            self.original = None;
+            // Loaded right away so it shows up in the side panel
+            self.applied.clear();
+        }
+
+        let Some(draft) = &mut self.draft else {
+            ui.label("Pick a loaded dbc to edit or start a new one");
+            return;
+        };
+        if let Some((original, _)) = &self.original {
+            ui.weak(format!(
+                "Edits are loaded as {} until they replace {original}",
+                draft.name
+            ));
+        }
+        let errors = draft.errors();
+        ui.horizontal(|ui| {
+            if ui
+                .button("Add raw signal")
+                .on_hover_text(format!(
+                    "Add {} from the side panel as a signal",
+                    raw_signal.name()
+                ))
+                .clicked()
+            {
+                let (message, signal) = draft.add_raw_signal(raw_signal);
+                self.message = message;
+                self.signal = Some(signal);
+            }
+            let export = ui
+                .add_enabled(errors.is_empty(), egui::Button::new("Export"))
+                .on_disabled_hover_text("Fix the errors first");
+            if export.clicked() {
+                // Imported KCD, SYM and ARXML files are exported as dbcs too
+                let name = match draft.name.rsplit_once('.') {
+                    Some((stem, _)) => format!("{stem}.dbc"),
+                    None => format!("{}.dbc", draft.name),
+                };
+                let text = draft.to_dbc_text();
+                spawn_local(async move {
+                    let Some(file) = AsyncFileDialog::new()
+                        .add_filter("DBC Files", &["dbc", "DBC"])
+                        .set_file_name(name)
+                        .save_file()
+                        .await
+                    else {
+                        return;
+                    };
+                    if let Err(error) = file.write(text.as_bytes()).await {
+                        log::error!("Could not export the dbc: {error}");
+                    }
+                });
+            }
+            if let Some((original, _)) = &self.original {
+                self.replace = ui
+                    .add_enabled(
+                        errors.is_empty(),
+                        egui::Button::new(format!("Replace {original}")),
+                    )
+                    .on_hover_text("Load the edits as the original dbc instead of the copy")
+                    .on_disabled_hover_text("Fix the errors first")
+                    .clicked();
+            }
+        });
+        for error in &errors {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        ui.separator();
+
+        egui::SidePanel::left("dbc_editor_messages")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (idx, message) in draft.messages.iter().enumerate() {
+                        let label = format!("{} {}", message.id, message.name);
+                        if ui.selectable_label(self.message == idx, label).clicked() {
+                            self.message = idx;
+                            self.signal = None;
+                        }
+                    }
+                    if ui.button("Add message").clicked() {
+                        let id = draft
+                            .messages
+                            .iter()
+                            .filter(|message| !message.id.extended)
+                            .map(|message| message.id.id + 1)
+                            .max()
+                            .unwrap_or(0x100);
+                        draft.messages.push(MessageDraft {
+                            id: RawCanMessageId {
+                                id,
+                                extended: false,
+                            },
+                            name: format!("Message_{id:X}"),
+                            size: 8,
+                            transmitter: String::new(),
+                            signals: Vec::new(),
+                        });
+                        self.message = draft.messages.len() - 1;
+                        self.signal = None;
+                    }
+                });
+            });
+
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            if self.message >= draft.messages.len() {
+                ui.label("No message selected");
+                return;
+            }
+            egui::ScrollArea::both().show(ui, |ui| {
+                let message = &mut draft.messages[self.message];
+                let remove = message_ui(ui, message);
+                signals_ui(ui, message, &mut self.signal);
+                if remove {
+                    draft.messages.remove(self.message);
+                    self.message = self.message.saturating_sub(1);
+                    self.signal = None;
+                }
+            });
+        });
+    }
+
+    fn edit(&mut self, mut draft: DbcDraft) {
+        draft.take_moves();
+        self.applied = draft.to_dbc_text();
+        self.draft = Some(draft);
+        self.message = 0;
+        self.signal = None;
+    }
+}
+
+// Fields of the message, returns whether it has to be removed
+fn message_ui(ui: &mut Ui, message: &mut MessageDraft) -> bool {
+    let mut remove = false;
+    egui::Grid::new("dbc_editor_message").show(ui, |ui| {
+        ui.label("ID");
+        ui.horizontal(|ui| {
+            let max_id = if message.id.extended {
+                0x1FFFFFFF
+            } else {
+                0x7FF
+            };
+            ui.add(
+                DragValue::new(&mut message.id.id)
+                    .hexadecimal(3, false, true)
+                    .prefix("0x")
+                    .range(0..=max_id),
+            );
+            ui.checkbox(&mut message.id.extended, "Extended");
+        });
+        ui.end_row();
+        ui.label("Name");
+        ui.text_edit_singleline(&mut message.name);
+        ui.end_row();
+        ui.label("Size");
+        ui.add(
+            DragValue::new(&mut message.size)
+                .range(0..=64)
+                .suffix(" bytes"),
+        );
+        ui.end_row();
+        ui.label("Transmitter");
+        ui.add(TextEdit::singleline(&mut message.transmitter).hint_text("None"));
+        ui.end_row();
+    });
+    if ui.button("Remove message").clicked() {
+        remove = true;
+    }
+    ui.separator();
+    remove
+}
+
+// One row per signal of the message and the value table of the selected one
+fn signals_ui(ui: &mut Ui, message: &mut MessageDraft, selected: &mut Option<usize>) {
+    let mut signal_to_remove = None;
+    egui::Grid::new("dbc_editor_signals")
+        .striped(true)
+        .show(ui, |ui| {
+            for header in [
+                "Signal",
+                "Multiplexing",
+                "Start bit",
+                "Size",
+                "Byte order",
+                "Type",
+                "Factor",
+                "Offset",
+                "Min",
+                "Max",
+                "Unit",
+                "Values",
+                "",
+            ] {
+                ui.strong(header);
+            }
+            ui.end_row();
+
+            for (idx, signal) in message.signals.iter_mut().enumerate() {
+                ui.add(TextEdit::singleline(&mut signal.name).desired_width(140.));
+                multiplexer_ui(ui, idx, &mut signal.multiplexer);
+                ui.add(DragValue::new(&mut signal.start_bit).range(0..=511));
+                ui.add(DragValue::new(&mut signal.size).range(1..=64));
+                egui::ComboBox::from_id_salt(("dbc_editor_byte_order", idx))
+                    .selected_text(match signal.byte_order {
+                        ByteOrder::LittleEndian => "Little endian",
+                        ByteOrder::BigEndian => "Big endian",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut signal.byte_order,
+                            ByteOrder::LittleEndian,
+                            "Little endian",
+                        );
+                        ui.selectable_value(
+                            &mut signal.byte_order,
+                            ByteOrder::BigEndian,
+                            "Big endian",
+                        );
+                    });
+                egui::ComboBox::from_id_salt(("dbc_editor_type", idx))
+                    .selected_text(signal.type_name())
+                    .show_ui(ui, |ui| {
+                        for (name, value_type, float) in SIGNAL_TYPES {
+                            if ui
+                                .selectable_label(signal.type_name() == name, name)
+                                .clicked()
+                            {
+                                signal.value_type = value_type;
+                                signal.float = float;
+                            }
+                        }
+                    });
+                for value in [
+                    &mut signal.factor,
+                    &mut signal.offset,
+                    &mut signal.min,
+                    &mut signal.max,
+                ] {
+                    ui.add(DragValue::new(value).speed(0.1));
+                }
+                ui.add(TextEdit::singleline(&mut signal.unit).desired_width(50.));
+                if ui
+                    .selectable_label(
+                        *selected == Some(idx),
+                        format!("{} values", signal.values.len()),
+                    )
+                    .clicked()
+                {
+                    *selected = (*selected != Some(idx)).then_some(idx);
+                }
+                if ui.button("🗑").on_hover_text("Remove signal").clicked() {
+                    signal_to_remove = Some(idx);
+                }
+                ui.end_row();
+            }
+        });
+
+    if ui.button("Add signal").clicked() {
+        let mut name = format!("Signal_{}", message.signals.len() + 1);
+        while message.signals.iter().any(|signal| signal.name == name) {
+            name.push('_');
+        }
+        message.signals.push(SignalDraft::new(name));
+    }
+    if let Some(idx) = signal_to_remove {
+        message.signals.remove(idx);
+        *selected = None;
+    }
+
+    let Some(signal) = selected.and_then(|idx| message.signals.get_mut(idx)) else {
+        return;
+    };
+    ui.separator();
+    ui.strong(format!("Values of {}", signal.name));
+    let mut value_to_remove = None;
+    egui::Grid::new("dbc_editor_values").show(ui, |ui| {
+        for (idx, (value, name)) in signal.values.iter_mut().enumerate() {
+            ui.add(DragValue::new(value));
+            ui.text_edit_singleline(name);
+            if ui.button("🗑").clicked() {
+                value_to_remove = Some(idx);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(idx) = value_to_remove {
+        signal.values.remove(idx);
+    }
+    if ui.button("Add value").clicked() {
+        let value = signal
+            .values
+            .iter()
+            .map(|(value, _)| value + 1.)
+            .fold(0., f64::max);
+        signal.values.push((value, String::new()));
+    }
+}
+
+fn multiplexer_ui(ui: &mut Ui, idx: usize, multiplexer: &mut MultiplexIndicator) {
+    let value = match *multiplexer {
+        MultiplexIndicator::MultiplexedSignal(value)
+        | MultiplexIndicator::MultiplexorAndMultiplexedSignal(value) => value,
+        _ => 0,
+    };
+    let options = [
+        ("None", MultiplexIndicator::Plain),
+        ("Multiplexor", MultiplexIndicator::Multiplexor),
+        ("Multiplexed", MultiplexIndicator::MultiplexedSignal(value)),
+        (
+            "Both",
+            MultiplexIndicator::MultiplexorAndMultiplexedSignal(value),
+        ),
+    ];
+    ui.horizontal(|ui| {
+        let selected = options
+            .iter()
+            .find(|(_, option)| option == multiplexer)
+            .map(|(name, _)| *name)
+            .unwrap_or_default();
+        egui::ComboBox::from_id_salt(("dbc_editor_multiplexer", idx))
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (name, option) in options {
+                    ui.selectable_value(multiplexer, option, name);
+                }
+            });
+        match multiplexer {
+            MultiplexIndicator::MultiplexedSignal(value)
+            | MultiplexIndicator::MultiplexorAndMultiplexedSignal(value) => {
+                ui.add(DragValue::new(value).prefix("when "))
+                    .on_hover_text("Value of the multiplexor that selects the signal");
+            }
+            _ => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut draft = DbcDraft::new(Arc::from("test.dbc"));
+        let id = RawCanMessageId {
+            id: 0x18FF0001,
+            extended: true,
+        };
+        draft.add_raw_signal(&RawSignal {
+            id,
+            start_bit: 7,
+            size: 16,
+            big_endian: true,
+            signed: true,
+        });
+        let message = &mut draft.messages[0];
+        message.transmitter = String::from("ECU");
+        message.signals[0].factor = 0.1;
+        message.signals[0].offset = -40.;
+        message.signals[0].unit = String::from("°C");
+        message.signals.push(SignalDraft {
+            multiplexer: MultiplexIndicator::Multiplexor,
+            start_bit: 16,
+            values: vec![(0., String::from("Off")), (1., String::from("On"))],
+            ..SignalDraft::new(String::from("Mode"))
+        });
+        message.signals.push(SignalDraft {
+            multiplexer: MultiplexIndicator::MultiplexedSignal(1),
+            start_bit: 32,
+            size: 32,
+            value_type: ValueType::Signed,
+            float: SignalExtendedValueType::IEEEfloat32Bit,
+            receivers: vec![String::from("Logger")],
+            ..SignalDraft::new(String::from("Level"))
+        });
+        assert_eq!(draft.errors(), Vec::<String>::new());
+
+        let text = draft.to_dbc_text();
+        let dbc = Dbc::new(Arc::from("test.dbc"), Arc::from(text.as_bytes())).unwrap();
+        let mut loaded = DbcDraft::from_dbc(&dbc);
+        assert_eq!(loaded.nodes, ["ECU", "Logger"]);
+        loaded.nodes.clear();
+        // Loaded signals know where they are
+        let moved = loaded.take_moves();
+        assert_eq!(moved[&(id, 2)], (id, 2));
+        draft.take_moves();
+        assert_eq!(loaded, draft);
+        assert_eq!(loaded.to_dbc_text(), text);
+
+        // Statements the editor does not show are written back as they were
+        let comment = "CM_ SG_ 2566848513 Level \"Tank level; in %\";";
+        let with_kept = text.replacen("BO_ ", "VAL_TABLE_ Modes 0 \"Off\" ;\n\nBO_ ", 1)
+            + "BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 10000;\n"
+            + comment
+            + "\nBA_ \"GenMsgCycleTime\" BO_ 2566848513 100;\n";
+        let dbc = Dbc::new(Arc::from("test.dbc"), Arc::from(with_kept.as_bytes())).unwrap();
+        let kept = DbcDraft::from_dbc(&dbc);
+        assert_eq!(kept.kept.len(), 4);
+        let rewritten = kept.to_dbc_text();
+        let dbc = Dbc::new(Arc::from("test.dbc"), Arc::from(rewritten.as_bytes())).unwrap();
+        assert_eq!(dbc.inner.comments().len(), 1);
+        assert_eq!(dbc.inner.attribute_values().len(), 1);
+        assert_eq!(dbc.inner.value_tables().len(), 1);
+        assert!(rewritten.contains(comment));
+
+        // A big endian signal starting at bit 7 of the last byte does not fit
+        loaded.messages[0].signals[0].start_bit = 63;
+        assert_eq!(
+            loaded.errors(),
+            ["Message_18FF0001.Signal_7 does not fit in the message"]
+        );
+    }
+}
@@ -0,0 +1,502 @@
+use can_dbc::{DBC, MultiplexIndicator, SignalExtendedValueType, ValDescription};
+
+// Everything needed to decode a signal out of a frame, looked up once instead of for every frame
+pub struct SignalDecoder<'a> {
+    signal: &'a can_dbc::Signal,
+    // IEEE float and double signals come from SIG_VALTYPE_
+    value_type: SignalExtendedValueType,
+    // Multiplexor values that have to match for the signal to be present in a frame
+    mux_conditions: Vec<MuxCondition>,
+    // Names of raw values from VAL_, empty for plain numeric signals
+    value_descriptions: &'a [ValDescription],
+}
+
+impl<'a> SignalDecoder<'a> {
+    pub fn new(dbc: &'a DBC, message: &can_dbc::Message, signal: &'a can_dbc::Signal) -> Self {
+        let mut mux_conditions = Vec::new();
+        push_mux_conditions(dbc, message, signal, &mut mux_conditions, 0);
+
+        Self {
+            signal,
+            value_type: dbc
+                .extended_value_type_for_signal(*message.message_id(), signal.name())
+                .copied()
+                .unwrap_or(SignalExtendedValueType::SignedOrUnsignedInteger),
+            mux_conditions,
+            value_descriptions: dbc
+                .value_descriptions_for_signal(*message.message_id(), signal.name())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_enum(&self) -> bool {
+        !self.value_descriptions.is_empty()
+    }
+
+    // VAL_ names raw values, so the decoded value is unscaled before looking it up
+    pub fn value_name(&self, value: f64) -> Option<&'a str> {
+        let raw_value = ((value - self.signal.offset()) / self.signal.factor()).round();
+        self.value_descriptions
+            .iter()
+            .find(|description| *description.a() == raw_value)
+            .map(|description| description.b().as_str())
+    }
+
+    // Returns None when the signal is not in the frame, either because the frame is too short or
+    // because the multiplexor selects other signals
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        if !self.is_selected(data) {
+            return None;
+        }
+
+        decode_signal(self.signal, self.value_type, data)
+    }
+
+    // Whether the multiplexors of the frame select this signal, always true for plain signals
+    pub fn is_selected(&self, data: &[u8]) -> bool {
+        self.mux_conditions.iter().all(|condition| {
+            extract_signal_value(
+                data,
+                condition.start_bit,
+                condition.size,
+                condition.byte_order,
+            )
+            .is_some_and(|mux_value| {
+                condition
+                    .ranges
+                    .iter()
+                    .any(|(min, max)| (*min..=*max).contains(&mux_value))
+            })
+        })
+    }
+}
+
+struct MuxCondition {
+    start_bit: usize,
+    size: usize,
+    byte_order: can_dbc::ByteOrder,
+    // Inclusive ranges of raw multiplexor values
+    ranges: Vec<(u64, u64)>,
+}
+
+impl MuxCondition {
+    fn new(multiplexor: &can_dbc::Signal, ranges: Vec<(u64, u64)>) -> Self {
+        Self {
+            start_bit: *multiplexor.start_bit() as usize,
+            size: *multiplexor.signal_size() as usize,
+            byte_order: *multiplexor.byte_order(),
+            ranges,
+        }
+    }
+}
+
+// Simple multiplexing uses the only `M` signal of the message, extended multiplexing (SG_MUL_VAL_)
+// names the multiplexor and its values explicitly, and that multiplexor can be multiplexed itself
+fn push_mux_conditions(
+    dbc: &DBC,
+    message: &can_dbc::Message,
+    signal: &can_dbc::Signal,
+    conditions: &mut Vec<MuxCondition>,
+    depth: usize,
+) {
+    let mux_value = match signal.multiplexer_indicator() {
+        MultiplexIndicator::MultiplexedSignal(value)
+        | MultiplexIndicator::MultiplexorAndMultiplexedSignal(value) => *value,
+        MultiplexIndicator::Multiplexor | MultiplexIndicator::Plain => return,
+    };
+    // Only a broken dbc can have a multiplexor cycle
+    if depth > message.signals().len() {
+        return;
+    }
+
+    let mut extended_multiplex = dbc
+        .extended_multiplex()
+        .iter()
+        .filter(|ext_mux| {
+            ext_mux.message_id() == message.message_id() && ext_mux.signal_name() == signal.name()
+        })
+        .peekable();
+
+    if extended_multiplex.peek().is_none() {
+        let Some(multiplexor) = message
+            .signals()
+            .iter()
+            .find(|signal| *signal.multiplexer_indicator() == MultiplexIndicator::Multiplexor)
+        else {
+            return;
+        };
+        conditions.push(MuxCondition::new(multiplexor, vec![(mux_value, mux_value)]));
+        return;
+    }
+
+    for ext_mux in extended_multiplex {
+        let Some(multiplexor) = message
+            .signals()
+            .iter()
+            .find(|signal| signal.name() == ext_mux.multiplexor_signal_name())
+        else {
+            continue;
+        };
+        conditions.push(MuxCondition::new(
+            multiplexor,
+            ext_mux
+                .mappings()
+                .iter()
+                .map(|mapping| (*mapping.min_value(), *mapping.max_value()))
+                .collect(),
+        ));
+        push_mux_conditions(dbc, message, multiplexor, conditions, depth + 1);
+    }
+}
+
+// https://docs.rs/can_decode/latest/src/can_decode/lib.rs.html#270-299
+// Could be made faster but i wont (simd + remove bitwise loops)
+// Returns None when the frame is too short to contain the whole signal or it holds a NaN or infinite
+// float, which cant be plotted
+fn decode_signal(
+    signal_def: &can_dbc::Signal,
+    extended_value_type: SignalExtendedValueType,
+    data: &[u8],
+) -> Option<f64> {
+    // Get signal properties
+    let start_bit = *signal_def.start_bit() as usize;
+    let signal_size = *signal_def.signal_size() as usize;
+    let byte_order = signal_def.byte_order();
+    let value_type = signal_def.value_type();
+    let factor = signal_def.factor();
+    let offset = signal_def.offset();
+
+    // Extract raw value based on byte order and signal properties
+    let raw_value = extract_signal_value(data, start_bit, signal_size, *byte_order)?;
+
+    // Convert to float or signed if needed
+    let raw_value = match extended_value_type {
+        SignalExtendedValueType::IEEEfloat32Bit if signal_size == 32 => {
+            f32::from_bits(raw_value as u32) as f64
+        }
+        SignalExtendedValueType::IEEEdouble64bit if signal_size == 64 => f64::from_bits(raw_value),
+        _ if *value_type == can_dbc::ValueType::Signed => {
+            // Move the sign bit to the top and shift back to extend it, works for 64 bit signals too
+            let unused_bits = 64 - signal_size;
+            ((raw_value << unused_bits) as i64 >> unused_bits) as f64
+        }
+        _ => raw_value as f64,
+    };
+
+    // Apply scaling
+    let scaled_value = raw_value * factor + offset;
+
+    scaled_value.is_finite().then_some(scaled_value)
+}
+
+// Bits of a frame without a dbc definition, as an integer
+pub fn decode_raw(
+    data: &[u8],
+    start_bit: usize,
+    size: usize,
+    byte_order: can_dbc::ByteOrder,
+    signed: bool,
+) -> Option<f64> {
+    let raw_value = extract_signal_value(data, start_bit, size, byte_order)?;
+    if signed {
+        let unused_bits = 64 - size;
+        Some(((raw_value << unused_bits) as i64 >> unused_bits) as f64)
+    } else {
+        Some(raw_value as f64)
+    }
+}
+
+fn extract_signal_value(
+    data: &[u8],
+    start_bit: usize,
+    size: usize,
+    byte_order: can_dbc::ByteOrder,
+) -> Option<u64> {
+    if size == 0 || size > 64 {
+        return None;
+    }
+
+    let mut result = 0u64;
+
+    match byte_order {
+        can_dbc::ByteOrder::LittleEndian => {
+            let start_byte = start_bit / 8;
+            let start_bit_in_byte = start_bit % 8;
+
+            if (start_bit + size).div_ceil(8) > data.len() {
+                return None;
+            }
+
+            let mut remaining_bits = size;
+            let mut current_byte = start_byte;
+            let mut bit_offset = start_bit_in_byte;
+
+            while remaining_bits > 0 && current_byte < data.len() {
+                let bits_in_this_byte = std::cmp::min(remaining_bits, 8 - bit_offset);
+                let mask = ((1u64 << bits_in_this_byte) - 1) << bit_offset;
+                let byte_value = ((data[current_byte] as u64) & mask) >> bit_offset;
+
+                result |= byte_value << (size - remaining_bits);
+
+                remaining_bits -= bits_in_this_byte;
+                current_byte += 1;
+                bit_offset = 0;
+            }
+        }
+        can_dbc::ByteOrder::BigEndian => {
+            // The start bit is the most significant one. The rest follow it going down inside its
+            // byte and then continue from the top bit of the next byte
+            let mut byte_idx = start_bit / 8;
+            let mut bit_idx = start_bit % 8;
+
+            for _ in 0..size {
+                let byte = *data.get(byte_idx)?;
+                result = (result << 1) | ((byte >> bit_idx) & 1) as u64;
+
+                if bit_idx == 0 {
+                    byte_idx += 1;
+                    bit_idx = 7;
+                } else {
+                    bit_idx -= 1;
+                }
+            }
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_DBC: &str = r#"
+VERSION ""
+
+NS_ :
+
+BS_:
+
+BU_: ECU
+
+BO_ 256 Motorola: 8 ECU
+ SG_ Speed : 7|16@0+ (1,0) [0|65535] "km/h" Vector__XXX
+ SG_ Temp : 3|12@0+ (0.1,-40) [-40|369.5] "degC" Vector__XXX
+ SG_ Torque : 5|10@0- (0.5,0) [-256|255.5] "Nm" Vector__XXX
+ SG_ Nibble : 7|4@0+ (1,0) [0|15] "" Vector__XXX
+ SG_ ThreeBytes : 11|20@0+ (1,0) [0|1048575] "" Vector__XXX
+
+BO_ 257 Motorola64: 8 ECU
+ SG_ Big : 7|64@0- (1,0) [0|0] "" Vector__XXX
+
+BO_ 258 Intel64: 8 ECU
+ SG_ BigLe : 0|64@1- (1,0) [0|0] "" Vector__XXX
+
+BO_ 259 Intel: 8 ECU
+ SG_ Rpm : 12|12@1+ (0.25,0) [0|1023.75] "rpm" Vector__XXX
+
+BO_ 260 MotorolaFd: 16 ECU
+ SG_ Far : 95|16@0+ (1,0) [0|65535] "" Vector__XXX
+
+BO_ 261 Floats: 16 ECU
+ SG_ Float : 0|32@1- (2,0) [0|0] "" Vector__XXX
+ SG_ Double : 71|64@0- (1,0) [0|0] "" Vector__XXX
+
+BO_ 262 Enums: 8 ECU
+ SG_ Gear : 0|4@1+ (1,0) [0|15] "" Vector__XXX
+ SG_ Mode : 8|8@1+ (10,-20) [0|0] "" Vector__XXX
+
+BO_ 263 Multiplexed: 8 ECU
+ SG_ Outer M : 0|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ Inner m1M : 8|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ Ranged m2 : 16|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ Nested m3 : 24|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ Simple m5 : 32|8@1+ (1,0) [0|255] "" Vector__XXX
+
+VAL_ 262 Gear 0 "Park" 1 "Reverse" 2 "Neutral" 3 "Drive" ;
+VAL_ 262 Mode 0 "Off" 2 "Eco" ;
+
+SIG_VALTYPE_ 261 Float : 1;
+SIG_VALTYPE_ 261 Double : 2;
+
+SG_MUL_VAL_ 263 Inner Outer 1-1;
+SG_MUL_VAL_ 263 Ranged Outer 2-4, 8-8;
+SG_MUL_VAL_ 263 Nested Inner 3-5;
+"#;
+
+    fn with_decoder<R>(signal_name: &str, f: impl FnOnce(&SignalDecoder) -> R) -> R {
+        let dbc = DBC::try_from(TEST_DBC).expect("Test dbc should parse");
+        let (message, signal) = dbc
+            .messages()
+            .iter()
+            .find_map(|message| {
+                message
+                    .signals()
+                    .iter()
+                    .find(|signal| signal.name() == signal_name)
+                    .map(|signal| (message, signal))
+            })
+            .expect("Signal should exist");
+
+        f(&SignalDecoder::new(&dbc, message, signal))
+    }
+
+    fn decode(signal_name: &str, data: &[u8]) -> Option<f64> {
+        with_decoder(signal_name, |decoder| decoder.decode(data))
+    }
+
+    fn assert_close(value: Option<f64>, expected: f64) {
+        let value = value.expect("Signal should be decoded");
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "decoded {value}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn motorola_aligned_word() {
+        assert_close(decode("Speed", &[0x12, 0x34, 0, 0, 0, 0, 0, 0]), 4660.);
+    }
+
+    #[test]
+    fn motorola_crossing_byte_boundary() {
+        assert_close(decode("Temp", &[0xAB, 0xCD, 0, 0, 0, 0, 0, 0]), 262.1);
+    }
+
+    #[test]
+    fn motorola_crossing_two_byte_boundaries() {
+        assert_close(
+            decode("ThreeBytes", &[0x00, 0x01, 0x23, 0x45, 0, 0, 0, 0]),
+            74565.,
+        );
+    }
+
+    #[test]
+    fn motorola_inside_one_byte() {
+        assert_close(decode("Nibble", &[0xA5, 0, 0, 0, 0, 0, 0, 0]), 10.);
+    }
+
+    #[test]
+    fn motorola_signed() {
+        assert_close(decode("Torque", &[0x3F, 0xF0, 0, 0, 0, 0, 0, 0]), -0.5);
+        assert_close(decode("Torque", &[0x12, 0x34, 0, 0, 0, 0, 0, 0]), 145.5);
+    }
+
+    #[test]
+    fn motorola_signed_64_bits() {
+        assert_close(
+            decode("Big", &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]),
+            -2.,
+        );
+    }
+
+    #[test]
+    fn intel_signed_64_bits() {
+        assert_close(
+            decode("BigLe", &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            -2.,
+        );
+    }
+
+    #[test]
+    fn intel_crossing_byte_boundary() {
+        assert_close(decode("Rpm", &[0x00, 0x30, 0x12, 0, 0, 0, 0, 0]), 72.75);
+    }
+
+    #[test]
+    fn motorola_past_byte_8() {
+        let mut data = [0; 16];
+        data[11] = 0xBE;
+        data[12] = 0xEF;
+        assert_close(decode("Far", &data), 48879.);
+    }
+
+    #[test]
+    fn float_signal() {
+        let mut data = [0; 16];
+        data[..4].copy_from_slice(&[0x00, 0x00, 0x48, 0x41]);
+        assert_close(decode("Float", &data), 25.);
+    }
+
+    #[test]
+    fn double_signal() {
+        let mut data = [0; 16];
+        data[8..].copy_from_slice(&[0xC0, 0x93, 0x4A, 0x45, 0x6D, 0x5C, 0xFA, 0xAD]);
+        assert_close(decode("Double", &data), -1234.5678);
+    }
+
+    #[test]
+    fn nan_is_absent() {
+        let mut data = [0; 16];
+        data[..4].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert_eq!(decode("Float", &data), None);
+    }
+
+    #[test]
+    fn short_frames_are_absent() {
+        assert_eq!(decode("Speed", &[0x12]), None);
+        assert_eq!(decode("Rpm", &[0x00, 0x30]), None);
+        assert_eq!(decode("Far", &[0; 12]), None);
+    }
+
+    #[test]
+    fn value_names() {
+        with_decoder("Gear", |decoder| {
+            assert!(decoder.is_enum());
+            assert_eq!(decoder.value_name(3.), Some("Drive"));
+            assert_eq!(decoder.value_name(7.), None);
+        });
+        // Raw 2 is 2 * 10 - 20 once scaled
+        with_decoder("Mode", |decoder| {
+            assert_eq!(decoder.value_name(0.), Some("Eco"));
+            assert_eq!(decoder.value_name(-20.), Some("Off"));
+        });
+        with_decoder("Speed", |decoder| assert!(!decoder.is_enum()));
+    }
+
+    #[test]
+    fn extended_multiplexing_ranges() {
+        // Outer, Inner, Ranged, Nested, Simple
+        let frame = |outer: u8, inner: u8| [outer, inner, 20, 30, 40, 0, 0, 0];
+        for outer in [2, 3, 4, 8] {
+            assert_close(decode("Ranged", &frame(outer, 0)), 20.);
+        }
+        for outer in [0, 1, 5, 7, 9] {
+            assert_eq!(decode("Ranged", &frame(outer, 0)), None);
+        }
+    }
+
+    #[test]
+    fn nested_multiplexors() {
+        let frame = |outer: u8, inner: u8| [outer, inner, 20, 30, 40, 0, 0, 0];
+        assert_close(decode("Inner", &frame(1, 4)), 4.);
+        assert_eq!(decode("Inner", &frame(2, 4)), None);
+        // Selected by Inner, which is only there when Outer is 1
+        for inner in [3, 4, 5] {
+            assert_close(decode("Nested", &frame(1, inner)), 30.);
+        }
+        assert_eq!(decode("Nested", &frame(1, 6)), None);
+        assert_eq!(decode("Nested", &frame(2, 4)), None);
+        // Signals without SG_MUL_VAL_ still follow the M signal
+        assert_close(decode("Simple", &frame(5, 0)), 40.);
+        assert_eq!(decode("Simple", &frame(1, 5)), None);
+    }
+
+    #[test]
+    fn raw_words() {
+        use can_dbc::ByteOrder::{BigEndian, LittleEndian};
+        let data = [0x12, 0x34, 0xFF, 0xFE];
+        assert_eq!(decode_raw(&data, 8, 8, LittleEndian, false), Some(52.));
+        assert_eq!(
+            decode_raw(&data, 0, 16, LittleEndian, false),
+            Some(0x3412 as f64)
+        );
+        // Big endian words start at the top bit of their first byte
+        assert_eq!(
+            decode_raw(&data, 7, 16, BigEndian, false),
+            Some(0x1234 as f64)
+        );
+        assert_eq!(decode_raw(&data, 23, 16, BigEndian, true), Some(-2.));
+        assert_eq!(decode_raw(&data, 16, 32, LittleEndian, false), None);
+    }
+}
@@ -0,0 +1,810 @@
+use egui::{Id, TextEdit, Ui};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::{
+    dbc::Dbcs,
+    series::{Channel, Resampler},
+};
+
+// A signal computed from dbc signals, like `(Battery.Voltage * Battery.Current) / 1000`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DerivedSignal {
+    pub name: Arc<str>,
+    pub unit: String,
+    pub expression: String,
+}
+
+impl DerivedSignal {
+    // Whether the expression parses and only uses signals of the loaded dbcs
+    pub fn check(&self, dbcs: &Dbcs) -> Result<(), String> {
+        let evaluator = Evaluator::new(&Expr::parse(&self.expression)?)?;
+        for (message, signal) in &evaluator.inputs {
+            dbcs.find_signal(message, signal)?;
+        }
+        Ok(())
+    }
+}
+
+// The derived signals of the session and the one being edited
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DerivedSignals {
+    pub signals: Vec<DerivedSignal>,
+    #[serde(skip)]
+    editor: Option<Editor>,
+}
+
+#[derive(Debug, Clone)]
+struct Editor {
+    // None for a new signal
+    idx: Option<usize>,
+    draft: DerivedSignal,
+}
+
+// What the plots have to know about
+pub enum DerivedChange {
+    Renamed(Arc<str>, Arc<str>),
+    Removed(Arc<str>),
+}
+
+impl DerivedSignals {
+    // The list of derived signals to drag into the plots and their editor window
+    pub fn ui(&mut self, ui: &mut Ui, dbcs: &Dbcs) -> Option<DerivedChange> {
+        let mut change = None;
+        let mut signal_to_remove = None;
+        for (idx, signal) in self.signals.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.dnd_drag_source(
+                    Id::new(("derived_signal", &*signal.name)),
+                    Channel::Derived(signal.name.clone()),
+                    |ui| {
+                        ui.label(&*signal.name);
+                    },
+                )
+                .response
+                .on_hover_text(&signal.expression);
+                if ui.small_button("Edit").clicked() {
+                    self.editor = Some(Editor {
+                        idx: Some(idx),
+                        draft: signal.clone(),
+                    });
+                }
+                if ui.small_button("Remove").clicked() {
+                    signal_to_remove = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = signal_to_remove {
+            change = Some(DerivedChange::Removed(self.signals.remove(idx).name));
+            self.editor = None;
+        }
+        if ui.button("New derived signal").clicked() {
+            self.editor = Some(Editor {
+                idx: None,
+                draft: DerivedSignal::default(),
+            });
+        }
+
+        change.or_else(|| self.editor_ui(ui.ctx(), dbcs))
+    }
+
+    fn editor_ui(&mut self, ctx: &egui::Context, dbcs: &Dbcs) -> Option<DerivedChange> {
+        let editor = self.editor.as_mut()?;
+        let name_taken = self
+            .signals
+            .iter()
+            .enumerate()
+            .any(|(idx, signal)| Some(idx) != editor.idx && signal.name == editor.draft.name);
+        let check = if editor.draft.name.is_empty() {
+            Err(String::from("The signal needs a name"))
+        } else if name_taken {
+            Err(format!(
+                "There is already a signal called {}",
+                editor.draft.name
+            ))
+        } else {
+            editor.draft.check(dbcs)
+        };
+
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        egui::Window::new("Derived signal")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("derived_signal_editor").show(ui, |ui| {
+                    let mut name = editor.draft.name.to_string();
+                    ui.label("Name");
+                    if ui.text_edit_singleline(&mut name).changed() {
+                        editor.draft.name = Arc::from(name);
+                    }
+                    ui.end_row();
+                    ui.label("Unit");
+                    ui.text_edit_singleline(&mut editor.draft.unit);
+                    ui.end_row();
+                    ui.label("Expression");
+                    ui.add(
+                        TextEdit::multiline(&mut editor.draft.expression)
+                            .code_editor()
+                            .hint_text("Battery.Voltage * Battery.Current / 1000"),
+                    );
+                    ui.end_row();
+                });
+
+                if let Err(error) = &check {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.collapsing("Help", |ui| ui.label(FUNCTIONS_HELP));
+                ui.horizontal(|ui| {
+                    save = ui
+                        .add_enabled(check.is_ok(), egui::Button::new("Save"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if !open || cancel {
+            self.editor = None;
+            return None;
+        }
+        if !save {
+            return None;
+        }
+
+        let Editor { idx, draft } = self.editor.take()?;
+        match idx.and_then(|idx| self.signals.get_mut(idx)) {
+            Some(signal) => {
+                let old_name = std::mem::replace(signal, draft).name;
+                (old_name != signal.name)
+                    .then(|| DerivedChange::Renamed(old_name, signal.name.clone()))
+            }
+            None => {
+                self.signals.push(draft);
+                None
+            }
+        }
+    }
+}
+
+const FUNCTIONS_HELP: &str = "Signals are written as Message.Signal, which only one of the \
+    loaded dbcs may have\n\
+    Operators: + - * / ^ < <= > >= == !=, comparisons give 1 or 0\n\
+    abs(x), sqrt(x), min(a, b), max(a, b)\n\
+    derivative(x): change per second\n\
+    integral(x): accumulated over seconds\n\
+    moving_average(x, seconds)";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    // Message and signal names
+    Signal(String, String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Function(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    // Only from the min and max functions
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Abs,
+    Sqrt,
+    Min,
+    Max,
+    Derivative,
+    Integral,
+    MovingAverage,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "abs" => Function::Abs,
+            "sqrt" => Function::Sqrt,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "derivative" => Function::Derivative,
+            "integral" => Function::Integral,
+            "moving_average" => Function::MovingAverage,
+            _ => return None,
+        })
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max | Function::MovingAverage => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl BinaryOp {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        let truth = |condition: bool| if condition { 1. } else { 0. };
+        match self {
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            BinaryOp::Mul => a * b,
+            BinaryOp::Div => a / b,
+            BinaryOp::Pow => a.powf(b),
+            BinaryOp::Lt => truth(a < b),
+            BinaryOp::Le => truth(a <= b),
+            BinaryOp::Gt => truth(a > b),
+            BinaryOp::Ge => truth(a >= b),
+            BinaryOp::Eq => truth(a == b),
+            BinaryOp::Ne => truth(a != b),
+            BinaryOp::Min => a.min(b),
+            BinaryOp::Max => a.max(b),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        let expr = parser.comparison()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some((token, at)) => Err(format!("Unexpected {token:?} at {at}")),
+        }
+    }
+
+    // Message and signal names of every signal used
+    pub fn signals(&self) -> Vec<(&str, &str)> {
+        let mut signals = Vec::new();
+        self.visit_signals(&mut signals);
+        signals
+    }
+
+    fn visit_signals<'a>(&'a self, signals: &mut Vec<(&'a str, &'a str)>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Signal(message, signal) => {
+                if !signals.contains(&(message.as_str(), signal.as_str())) {
+                    signals.push((message, signal));
+                }
+            }
+            Expr::Neg(expr) => expr.visit_signals(signals),
+            Expr::Binary(_, a, b) => {
+                a.visit_signals(signals);
+                b.visit_signals(signals);
+            }
+            Expr::Function(_, args) => args.iter().for_each(|arg| arg.visit_signals(signals)),
+        }
+    }
+}
+
+// An expression that is evaluated on the points of its signals as they arrive, keeping what it
+// needs to carry on from the points it already evaluated
+pub struct Evaluator {
+    // Message and signal names of the signals used, in the order `advance` takes their points
+    pub inputs: Vec<(String, String)>,
+    root: Stream,
+}
+
+impl Evaluator {
+    pub fn new(expr: &Expr) -> Result<Evaluator, String> {
+        let inputs: Vec<(String, String)> = expr
+            .signals()
+            .into_iter()
+            .map(|(message, signal)| (message.to_owned(), signal.to_owned()))
+            .collect();
+        match compile(expr, &inputs)? {
+            Compiled::Stream(root) => Ok(Evaluator { inputs, root }),
+            Compiled::Constant(_) => Err(String::from("The expression does not use any signal")),
+        }
+    }
+
+    // Points of the result at every time any of the signals has a new value, for the points the
+    // signals got since the last call. `inputs` has every point of each signal so far, NaN for
+    // gaps. None when the new points are not after the ones already evaluated, the evaluator has
+    // to start over then
+    pub fn advance(&mut self, inputs: &[&[[f64; 2]]]) -> Option<Vec<[f64; 2]>> {
+        self.root.advance(inputs)
+    }
+}
+
+enum Compiled {
+    Constant(f64),
+    Stream(Stream),
+}
+
+// A part of the expression that depends on signals, with the state it needs between points
+enum Stream {
+    // Position of the signal in the inputs and how many of its points were read
+    Signal {
+        input: usize,
+        read: usize,
+    },
+    Map(Box<Stream>, fn(f64) -> f64),
+    ConstantLeft(BinaryOp, f64, Box<Stream>),
+    ConstantRight(BinaryOp, Box<Stream>, f64),
+    Pair(BinaryOp, Box<Stream>, Box<Stream>, Resampler),
+    // Last point
+    Derivative(Box<Stream>, Option<[f64; 2]>),
+    Integral {
+        arg: Box<Stream>,
+        last: Option<[f64; 2]>,
+        total: f64,
+    },
+    // Values inside the window and their sum
+    MovingAverage {
+        arg: Box<Stream>,
+        window: f64,
+        values: VecDeque<[f64; 2]>,
+        sum: f64,
+    },
+}
+
+impl Compiled {
+    fn map(self, f: fn(f64) -> f64) -> Compiled {
+        match self {
+            Compiled::Constant(value) => Compiled::Constant(f(value)),
+            Compiled::Stream(stream) => Compiled::Stream(Stream::Map(Box::new(stream), f)),
+        }
+    }
+
+    fn stream(self) -> Result<Box<Stream>, String> {
+        match self {
+            Compiled::Stream(stream) => Ok(Box::new(stream)),
+            Compiled::Constant(_) => Err(String::from(
+                "derivative, integral and moving_average need a signal",
+            )),
+        }
+    }
+}
+
+fn compile(expr: &Expr, inputs: &[(String, String)]) -> Result<Compiled, String> {
+    Ok(match expr {
+        Expr::Number(number) => Compiled::Constant(*number),
+        Expr::Signal(message, signal) => Compiled::Stream(Stream::Signal {
+            input: inputs
+                .iter()
+                .position(|input| input.0 == *message && input.1 == *signal)
+                .unwrap_or_default(),
+            read: 0,
+        }),
+        Expr::Neg(expr) => compile(expr, inputs)?.map(|value| -value),
+        Expr::Binary(op, a, b) => combine(*op, compile(a, inputs)?, compile(b, inputs)?),
+        Expr::Function(function, args) => {
+            let arg = compile(&args[0], inputs)?;
+            match function {
+                Function::Abs => arg.map(f64::abs),
+                Function::Sqrt => arg.map(f64::sqrt),
+                Function::Min => combine(BinaryOp::Min, arg, compile(&args[1], inputs)?),
+                Function::Max => combine(BinaryOp::Max, arg, compile(&args[1], inputs)?),
+                Function::Derivative => Compiled::Stream(Stream::Derivative(arg.stream()?, None)),
+                Function::Integral => Compiled::Stream(Stream::Integral {
+                    arg: arg.stream()?,
+                    last: None,
+                    total: 0.,
+                }),
+                Function::MovingAverage => {
+                    let Compiled::Constant(window) = compile(&args[1], inputs)? else {
+                        return Err(String::from(
+                            "The window of moving_average has to be a number of seconds",
+                        ));
+                    };
+                    Compiled::Stream(Stream::MovingAverage {
+                        arg: arg.stream()?,
+                        window,
+                        values: VecDeque::new(),
+                        sum: 0.,
+                    })
+                }
+            }
+        }
+    })
+}
+
+fn combine(op: BinaryOp, a: Compiled, b: Compiled) -> Compiled {
+    Compiled::Stream(match (a, b) {
+        (Compiled::Constant(a), Compiled::Constant(b)) => {
+            return Compiled::Constant(op.apply(a, b));
+        }
+        (Compiled::Stream(a), Compiled::Constant(b)) => Stream::ConstantRight(op, Box::new(a), b),
+        (Compiled::Constant(a), Compiled::Stream(b)) => Stream::ConstantLeft(op, a, Box::new(b)),
+        (Compiled::Stream(a), Compiled::Stream(b)) => {
+            Stream::Pair(op, Box::new(a), Box::new(b), Resampler::default())
+        }
+    })
+}
+
+impl Stream {
+    fn advance(&mut self, inputs: &[&[[f64; 2]]]) -> Option<Vec<[f64; 2]>> {
+        let mut points = Vec::new();
+        match self {
+            Stream::Signal { input, read } => {
+                points.extend_from_slice(inputs.get(*input)?.get(*read..)?);
+                *read += points.len();
+            }
+            Stream::Map(arg, f) => {
+                points = arg.advance(inputs)?;
+                points.iter_mut().for_each(|point| point[1] = f(point[1]));
+            }
+            Stream::ConstantLeft(op, a, arg) => {
+                points = arg.advance(inputs)?;
+                points
+                    .iter_mut()
+                    .for_each(|point| point[1] = op.apply(*a, point[1]));
+            }
+            Stream::ConstantRight(op, arg, b) => {
+                points = arg.advance(inputs)?;
+                points
+                    .iter_mut()
+                    .for_each(|point| point[1] = op.apply(point[1], *b));
+            }
+            Stream::Pair(op, a, b, resampler) => {
+                let (a, b) = (a.advance(inputs)?, b.advance(inputs)?);
+                points = resampler
+                    .merge(&a, &b)?
+                    .into_iter()
+                    .map(|[time, a, b]| [time, op.apply(a, b)])
+                    .collect();
+            }
+            Stream::Derivative(arg, last) => {
+                for point in arg.advance(inputs)? {
+                    if let Some(last) = last
+                        && point[0] > last[0]
+                    {
+                        points.push([point[0], (point[1] - last[1]) / (point[0] - last[0])]);
+                    }
+                    *last = Some(point);
+                }
+            }
+            // Trapezoidal, the gaps add nothing
+            Stream::Integral { arg, last, total } => {
+                for point in arg.advance(inputs)? {
+                    if let Some(last) = last {
+                        let area = (last[1] + point[1]) / 2. * (point[0] - last[0]);
+                        if area.is_finite() {
+                            *total += area;
+                        }
+                    }
+                    points.push([point[0], *total]);
+                    *last = Some(point);
+                }
+            }
+            // Mean of the values in the last `window` seconds at every point, skipping the gaps
+            Stream::MovingAverage {
+                arg,
+                window,
+                values,
+                sum,
+            } => {
+                for point in arg.advance(inputs)? {
+                    if point[1].is_nan() {
+                        continue;
+                    }
+                    values.push_back(point);
+                    *sum += point[1];
+                    while values.len() > 1 && values[0][0] <= point[0] - *window {
+                        *sum -= values.pop_front().unwrap_or_default()[1];
+                    }
+                    points.push([point[0], *sum / values.len() as f64]);
+                }
+            }
+        }
+        Some(points)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, String> {
+    const OPS: [&str; 15] = [
+        "<=", ">=", "==", "!=", "<", ">", "+", "-", "*", "/", "^", "(", ")", ",", ".",
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        rest = &rest[start..];
+        let at = input.len() - rest.len();
+        let first = rest.chars().next().unwrap_or_default();
+
+        let len = if first.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            let number = rest[..len]
+                .parse()
+                .map_err(|_| format!("Invalid number {} at {at}", &rest[..len]))?;
+            tokens.push((Token::Number(number), at));
+            len
+        } else if first.is_alphabetic() || first == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push((Token::Ident(rest[..len].to_owned()), at));
+            len
+        } else if let Some(op) = OPS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push((Token::Op(op), at));
+            op.len()
+        } else {
+            return Err(format!("Unexpected '{first}' at {at}"));
+        };
+        rest = &rest[len..];
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Op(op), _)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect_op(&mut self, expected: &str) -> Result<(), String> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Op(op), _)) if *op == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some((token, at)) => Err(format!("Expected '{expected}' at {at}, found {token:?}")),
+            None => Err(format!("Expected '{expected}' at the end")),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let expr = self.additive()?;
+        let op = match self.peek_op() {
+            Some("<") => BinaryOp::Lt,
+            Some("<=") => BinaryOp::Le,
+            Some(">") => BinaryOp::Gt,
+            Some(">=") => BinaryOp::Ge,
+            Some("==") => BinaryOp::Eq,
+            Some("!=") => BinaryOp::Ne,
+            _ => return Ok(expr),
+        };
+        self.pos += 1;
+        Ok(Expr::Binary(op, Box::new(expr), Box::new(self.additive()?)))
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+        loop {
+            let op = match self.peek_op() {
+                Some("+") => BinaryOp::Add,
+                Some("-") => BinaryOp::Sub,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = match self.peek_op() {
+                Some("*") => BinaryOp::Mul,
+                Some("/") => BinaryOp::Div,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek_op() == Some("-") {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+
+        let base = self.primary()?;
+        if self.peek_op() == Some("^") {
+            self.pos += 1;
+            // Right associative, and -2^2 is -(2^2)
+            return Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let Some((token, at)) = self.tokens.get(self.pos).cloned() else {
+            return Err(String::from("Unexpected end of the expression"));
+        };
+        self.pos += 1;
+
+        match token {
+            Token::Number(number) => Ok(Expr::Number(number)),
+            Token::Op("(") => {
+                let expr = self.comparison()?;
+                self.expect_op(")")?;
+                Ok(expr)
+            }
+            Token::Ident(name) if self.peek_op() == Some("(") => {
+                let function = Function::from_name(&name)
+                    .ok_or_else(|| format!("Unknown function {name} at {at}"))?;
+                self.pos += 1;
+                let mut args = vec![self.comparison()?];
+                while self.peek_op() == Some(",") {
+                    self.pos += 1;
+                    args.push(self.comparison()?);
+                }
+                self.expect_op(")")?;
+                if args.len() != function.arity() {
+                    return Err(format!(
+                        "{name} at {at} takes {} arguments, not {}",
+                        function.arity(),
+                        args.len()
+                    ));
+                }
+                Ok(Expr::Function(function, args))
+            }
+            Token::Ident(message) => {
+                self.expect_op(".")?;
+                match self.tokens.get(self.pos).cloned() {
+                    Some((Token::Ident(signal), _)) => {
+                        self.pos += 1;
+                        Ok(Expr::Signal(message, signal))
+                    }
+                    _ => Err(format!("Expected a signal name after {message}.")),
+                }
+            }
+            token => Err(format!("Unexpected {token:?} at {at}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(expression: &str) -> Result<Vec<[f64; 2]>, String> {
+        let speed = [[0., 10.], [1., 20.], [2., 40.]];
+        let torque = [[0.5, 2.], [1.5, f64::NAN], [2.5, 4.]];
+        let mut evaluator = Evaluator::new(&Expr::parse(expression)?)?;
+        let inputs = evaluator
+            .inputs
+            .iter()
+            .map(
+                |(message, signal)| match (message.as_str(), signal.as_str()) {
+                    ("Motor", "Speed") => Ok(&speed[..]),
+                    ("Motor", "Torque") => Ok(&torque[..]),
+                    _ => Err(format!("Unknown signal {message}.{signal}")),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(evaluator.advance(&inputs).unwrap())
+    }
+
+    #[test]
+    fn precedence() {
+        let expr = Expr::parse("1 + 2 * 3 ^ 2 > -4").unwrap();
+        let expected = Expr::Binary(
+            BinaryOp::Gt,
+            Box::new(Expr::Binary(
+                BinaryOp::Add,
+                Box::new(Expr::Number(1.)),
+                Box::new(Expr::Binary(
+                    BinaryOp::Mul,
+                    Box::new(Expr::Number(2.)),
+                    Box::new(Expr::Binary(
+                        BinaryOp::Pow,
+                        Box::new(Expr::Number(3.)),
+                        Box::new(Expr::Number(2.)),
+                    )),
+                )),
+            )),
+            Box::new(Expr::Neg(Box::new(Expr::Number(4.)))),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Expr::parse("Motor.").is_err());
+        assert!(Expr::parse("(1 + 2").is_err());
+        assert!(Expr::parse("abs(1, 2)").is_err());
+        assert!(Expr::parse("foo(1)").is_err());
+        assert!(Expr::parse("1 $ 2").is_err());
+        assert!(Expr::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn signals_are_held_until_their_next_point() {
+        let points = evaluate("Motor.Speed * Motor.Torque").unwrap();
+        let times: Vec<f64> = points.iter().map(|point| point[0]).collect();
+        assert_eq!(times, [0.5, 1., 1.5, 2., 2.5]);
+        assert_eq!(points[..2], [[0.5, 20.], [1., 40.]]);
+        // The gap in Torque lasts until its next point
+        assert!(points[2][1].is_nan() && points[3][1].is_nan());
+        assert_eq!(points[4], [2.5, 160.]);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(
+            evaluate("derivative(Motor.Speed)").unwrap(),
+            [[1., 10.], [2., 20.]]
+        );
+        assert_eq!(
+            evaluate("integral(Motor.Speed)").unwrap(),
+            [[0., 0.], [1., 15.], [2., 45.]]
+        );
+        assert_eq!(
+            evaluate("moving_average(Motor.Speed, 1.5)").unwrap(),
+            [[0., 10.], [1., 15.], [2., 30.]]
+        );
+        assert_eq!(
+            evaluate("max(Motor.Speed, 15) >= 20").unwrap(),
+            [[0., 0.], [1., 1.], [2., 1.]]
+        );
+        assert!(evaluate("Motor.Rpm").is_err());
+        assert!(evaluate("abs(-3)").is_err());
+        assert!(evaluate("moving_average(Motor.Speed, Motor.Speed)").is_err());
+    }
+
+    #[test]
+    fn new_points_carry_on_the_evaluation() {
+        let expr = Expr::parse(
+            "integral(Motor.Speed) + derivative(Motor.Torque) * moving_average(Motor.Speed, 1.5)",
+        )
+        .unwrap();
+        let speed: Vec<[f64; 2]> = (0..5)
+            .map(|time| [time as f64, (time * 7 % 5) as f64])
+            .collect();
+        let torque: Vec<[f64; 2]> = (0..5)
+            .map(|time| [time as f64 + 0.5, (time * 3 % 4) as f64])
+            .collect();
+        let whole = Evaluator::new(&expr)
+            .unwrap()
+            .advance(&[&speed, &torque])
+            .unwrap();
+
+        let mut evaluator = Evaluator::new(&expr).unwrap();
+        let mut points = Vec::new();
+        for (speed_len, torque_len) in [(1, 0), (2, 2), (4, 3), (4, 3), (5, 5)] {
+            points.extend(
+                evaluator
+                    .advance(&[&speed[..speed_len], &torque[..torque_len]])
+                    .unwrap(),
+            );
+        }
+        assert_eq!(points, whole);
+
+        // Points before the ones already evaluated need a new start
+        let mut evaluator = Evaluator::new(&expr).unwrap();
+        evaluator.advance(&[&speed[..3], &torque[..0]]).unwrap();
+        assert_eq!(evaluator.advance(&[&speed[..3], &torque[..2]]), None);
+    }
+}
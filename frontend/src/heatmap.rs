@@ -0,0 +1,421 @@
+use chrono::{DateTime, Utc};
+use egui::{Align2, Color32, FontId, Rect, Sense, Ui, epaint::Hsva, vec2};
+use std::collections::HashSet;
+
+use crate::{
+    messages::{Bus, Message, Messages, RawCanMessageId},
+    plots::Plots,
+    raw::RawSignal,
+};
+
+const BIT_CELL_SIZE: egui::Vec2 = vec2(40., 24.);
+const WATERFALL_ROWS: usize = 256;
+const WATERFALL_BYTE_WIDTH: f32 = 24.;
+
+// How often every bit of an id changes, to find counters, checksums and signals in frames no dbc
+// describes
+#[derive(Default)]
+pub struct BitHeatmap {
+    pub open: bool,
+    id: Option<RawCanMessageId>,
+    // Frames of the same id on different buses are different streams, so only one is compared
+    bus: Option<Bus>,
+    between_cursors: bool,
+    toggles: BitToggles,
+}
+
+#[derive(Default)]
+struct BitToggles {
+    // What the counts were made from
+    id: Option<RawCanMessageId>,
+    bus: Option<Bus>,
+    revision: u64,
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    // Position in the frames of the id of the next frame to count
+    next_frame: usize,
+    // Positions of the frames of the bus that were counted, for the waterfall
+    counted: Vec<usize>,
+    last: Option<Vec<u8>>,
+    // Times each bit changed from one frame to the next, numbered like little endian dbc bits
+    toggles: Vec<u64>,
+    // Pairs of consecutive frames compared
+    comparisons: u64,
+}
+
+impl BitToggles {
+    // Counts the new frames in the range, or all of them again when something else changed
+    fn update(
+        &mut self,
+        messages: &Messages,
+        id: RawCanMessageId,
+        bus: Bus,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) {
+        let frames = messages
+            .frames
+            .get(&id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if self.id != Some(id)
+            || self.bus != Some(bus)
+            || self.revision != messages.revision
+            || self.range != range
+        {
+            *self = BitToggles {
+                id: Some(id),
+                bus: Some(bus),
+                revision: messages.revision,
+                range,
+                next_frame: frame_range(frames, range).start,
+                ..Default::default()
+            };
+        }
+
+        let end = frame_range(frames, range).end;
+        for (idx, frame) in frames
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(self.next_frame)
+            .filter(|(_, frame)| frame.bus == bus)
+        {
+            self.add(&frame.contents);
+            self.counted.push(idx);
+        }
+        self.next_frame = end.max(self.next_frame);
+    }
+
+    fn add(&mut self, data: &[u8]) {
+        if let Some(last) = &mut self.last {
+            if self.toggles.len() < data.len() * 8 {
+                self.toggles.resize(data.len() * 8, 0);
+            }
+            for (byte_idx, (last_byte, byte)) in last.iter().zip(data).enumerate() {
+                let changed = last_byte ^ byte;
+                for bit in 0..8 {
+                    if changed >> bit & 1 == 1 {
+                        self.toggles[byte_idx * 8 + bit] += 1;
+                    }
+                }
+            }
+            self.comparisons += 1;
+            last.clear();
+            last.extend_from_slice(data);
+        } else {
+            self.last = Some(data.to_vec());
+            self.toggles.resize(data.len() * 8, 0);
+        }
+    }
+
+    // Fraction of the frames in which the bit changed
+    fn rate(&self, bit: usize) -> f64 {
+        if self.comparisons == 0 {
+            return 0.;
+        }
+        self.toggles.get(bit).copied().unwrap_or_default() as f64 / self.comparisons as f64
+    }
+}
+
+// Positions of the frames inside the time range
+fn frame_range(
+    frames: &[Message],
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+) -> std::ops::Range<usize> {
+    match range {
+        Some((start, end)) => {
+            frames.partition_point(|frame| frame.timestamp < start)
+                ..frames.partition_point(|frame| frame.timestamp <= end)
+        }
+        None => 0..frames.len(),
+    }
+}
+
+impl BitHeatmap {
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        messages: &Messages,
+        hidden_buses: &HashSet<Bus>,
+        plots: &Plots,
+        raw_signal: &mut RawSignal,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Bit changes")
+            .open(&mut open)
+            .default_size([500., 600.])
+            .show(ctx, |ui| {
+                self.ui(ui, messages, hidden_buses, plots, raw_signal)
+            });
+        self.open = open;
+    }
+
+    fn ui(
+        &mut self,
+        ui: &mut Ui,
+        messages: &Messages,
+        hidden_buses: &HashSet<Bus>,
+        plots: &Plots,
+        raw_signal: &mut RawSignal,
+    ) {
+        let mut ids: Vec<&RawCanMessageId> = messages.frames.keys().collect();
+        ids.sort();
+        let Some(id) = self
+            .id
+            .filter(|id| ids.contains(&id))
+            .or(ids.first().copied().copied())
+        else {
+            ui.label("No frames loaded");
+            return;
+        };
+        self.id = Some(id);
+        let buses: Vec<Bus> = messages
+            .buses
+            .iter()
+            .copied()
+            .filter(|bus| !hidden_buses.contains(bus))
+            .collect();
+        let Some(bus) = self
+            .bus
+            .filter(|bus| buses.contains(bus))
+            .or(buses.first().copied())
+        else {
+            ui.label("Every bus is hidden");
+            return;
+        };
+        self.bus = Some(bus);
+
+        let cursor_range = plots.cursor_range();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("ID")
+                .selected_text(id.to_string())
+                .show_ui(ui, |ui| {
+                    for id in &ids {
+                        ui.selectable_value(&mut self.id, Some(**id), id.to_string());
+                    }
+                });
+            egui::ComboBox::from_label("Bus")
+                .selected_text(bus.as_str())
+                .show_ui(ui, |ui| {
+                    for bus in &buses {
+                        ui.selectable_value(&mut self.bus, Some(*bus), bus.as_str());
+                    }
+                });
+            ui.separator();
+            ui.selectable_value(&mut self.between_cursors, false, "Whole log");
+            ui.add_enabled_ui(cursor_range.is_some(), |ui| {
+                ui.selectable_value(&mut self.between_cursors, true, "Between cursors")
+                    .on_disabled_hover_text("Turn the cursors on to pick a time window");
+            });
+        });
+
+        let range = cursor_range.filter(|_| self.between_cursors);
+        self.toggles.update(messages, id, bus, range);
+        ui.label(format!(
+            "{} frames compared, click a bit to start a raw signal there",
+            self.toggles.comparisons
+        ));
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.bits_ui(ui, id, raw_signal);
+            ui.separator();
+            ui.strong("Bytes over time");
+            let frames = messages
+                .frames
+                .get(&id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            waterfall_ui(ui, frames, &self.toggles.counted);
+        });
+    }
+
+    // One row per byte with its most significant bit on the left, like a hex dump
+    fn bits_ui(&self, ui: &mut Ui, id: RawCanMessageId, raw_signal: &mut RawSignal) {
+        let bytes = self.toggles.toggles.len() / 8;
+        let (rect, response) = ui.allocate_exact_size(
+            vec2(BIT_CELL_SIZE.x * 9., BIT_CELL_SIZE.y * (bytes + 1) as f32),
+            Sense::click(),
+        );
+        let painter = ui.painter_at(rect);
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(11.);
+
+        let cell_rect = |row: usize, column: usize| {
+            Rect::from_min_size(
+                rect.min
+                    + vec2(
+                        column as f32 * BIT_CELL_SIZE.x,
+                        row as f32 * BIT_CELL_SIZE.y,
+                    ),
+                BIT_CELL_SIZE,
+            )
+        };
+        for column in 1..9 {
+            painter.text(
+                cell_rect(0, column).center(),
+                Align2::CENTER_CENTER,
+                format!("{}", 8 - column),
+                font.clone(),
+                text_color,
+            );
+        }
+
+        let mut hovered_bit = None;
+        for byte in 0..bytes {
+            painter.text(
+                cell_rect(byte + 1, 0).center(),
+                Align2::CENTER_CENTER,
+                format!("Byte {byte}"),
+                font.clone(),
+                text_color,
+            );
+            for column in 1..9 {
+                let bit = byte * 8 + 8 - column;
+                let cell = cell_rect(byte + 1, column).shrink(1.);
+                let rate = self.toggles.rate(bit);
+                painter.rect_filled(cell, 2., toggle_color(rate));
+                painter.text(
+                    cell.center(),
+                    Align2::CENTER_CENTER,
+                    format!("{:.0}", rate * 100.),
+                    font.clone(),
+                    Color32::WHITE,
+                );
+                if response.hover_pos().is_some_and(|pos| cell.contains(pos)) {
+                    hovered_bit = Some(bit);
+                }
+            }
+        }
+
+        let Some(bit) = hovered_bit else {
+            return;
+        };
+        if response.clicked() {
+            *raw_signal = RawSignal {
+                id,
+                start_bit: bit as u16,
+                size: 1,
+                ..Default::default()
+            };
+        }
+        response.on_hover_text(format!(
+            "Bit {bit} (byte {} bit {}): changed {} times, {:.1}% of the frames",
+            bit / 8,
+            bit % 8,
+            self.toggles.toggles[bit],
+            self.toggles.rate(bit) * 100.
+        ));
+    }
+}
+
+// Gray for bits that never change, then blue for the ones that rarely do up to red for the ones
+// that change in every frame
+fn toggle_color(rate: f64) -> Color32 {
+    if rate == 0. {
+        return Color32::from_gray(90);
+    }
+    Hsva::new(0.66 * (1. - rate as f32), 0.8, 0.7, 1.).into()
+}
+
+// Every byte of the frames at those positions as a column, from the first frame at the top to the
+// last one at the bottom, brighter for higher values. Long logs only show some evenly spaced frames
+fn waterfall_ui(ui: &mut Ui, frames: &[Message], shown: &[usize]) {
+    let rows = shown.len().min(WATERFALL_ROWS);
+    let frame_at_row = |row: usize| &frames[shown[row * shown.len() / rows]];
+    let Some(bytes) = (0..rows).map(|row| frame_at_row(row).contents.len()).max() else {
+        return;
+    };
+    let row_height = 300. / rows as f32;
+    let (rect, response) = ui.allocate_exact_size(
+        vec2(WATERFALL_BYTE_WIDTH * bytes as f32, 300.),
+        Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+
+    for row in 0..rows {
+        let frame = frame_at_row(row);
+        for (byte_idx, byte) in frame.contents.iter().enumerate() {
+            let cell = Rect::from_min_size(
+                rect.min
+                    + vec2(
+                        byte_idx as f32 * WATERFALL_BYTE_WIDTH,
+                        row as f32 * row_height,
+                    ),
+                vec2(WATERFALL_BYTE_WIDTH, row_height),
+            );
+            let value = *byte as f32 / 255.;
+            painter.rect_filled(
+                cell,
+                0.,
+                Hsva::new(0.7 - 0.55 * value, 0.8, 0.2 + 0.8 * value, 1.),
+            );
+        }
+    }
+
+    if let Some(pos) = response.hover_pos() {
+        let row = (((pos.y - rect.min.y) / row_height) as usize).min(rows - 1);
+        let byte_idx = ((pos.x - rect.min.x) / WATERFALL_BYTE_WIDTH) as usize;
+        let frame = frame_at_row(row);
+        if let Some(byte) = frame.contents.get(byte_idx) {
+            response.on_hover_text(format!(
+                "{}\nByte {byte_idx}: 0x{byte:02X} ({byte})",
+                frame.timestamp.format("%H:%M:%S%.6f")
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles() {
+        let messages = Messages::from_string(
+            "(1.0) can0 100#0001\n(2.0) can0 100#0102\n(3.0) can0 100#0203\n(4.0) can0 100#0300"
+                .into(),
+        );
+        let id = RawCanMessageId {
+            id: 0x100,
+            extended: false,
+        };
+        let can0 = Bus::new("can0").unwrap();
+        let mut toggles = BitToggles::default();
+        toggles.update(&messages, id, can0, None);
+        assert_eq!(toggles.comparisons, 3);
+        // A counter in byte 0, its lowest bit changes every frame
+        assert_eq!(toggles.toggles[..3], [3, 1, 0]);
+        assert_eq!(toggles.toggles[8..10], [3, 2]);
+        assert_eq!(toggles.rate(0), 1.);
+
+        let start = DateTime::from_timestamp(2, 0).unwrap();
+        let end = DateTime::from_timestamp(3, 0).unwrap();
+        toggles.update(&messages, id, can0, Some((start, end)));
+        assert_eq!(toggles.comparisons, 1);
+        assert_eq!(toggles.toggles[..2], [1, 1]);
+    }
+
+    #[test]
+    fn buses_are_compared_apart() {
+        // A counter on can0 and a constant on can1, interleaved
+        let messages = Messages::from_string(
+            "(1.0) can0 100#01\n(1.5) can1 100#F0\n(2.0) can0 100#02\n(2.5) can1 100#F0\n\
+             (3.0) can0 100#03"
+                .into(),
+        );
+        let id = RawCanMessageId {
+            id: 0x100,
+            extended: false,
+        };
+        let mut toggles = BitToggles::default();
+        toggles.update(&messages, id, Bus::new("can1").unwrap(), None);
+        assert_eq!(toggles.comparisons, 1);
+        assert_eq!(toggles.toggles, [0; 8]);
+        assert_eq!(toggles.counted, [1, 3]);
+
+        toggles.update(&messages, id, Bus::new("can0").unwrap(), None);
+        assert_eq!(toggles.comparisons, 2);
+        assert_eq!(toggles.toggles[..2], [2, 1]);
+        assert_eq!(toggles.counted, [0, 2, 4]);
+    }
+}
@@ -0,0 +1,1433 @@
+use can_dbc::{ByteOrder, MultiplexIndicator, SignalExtendedValueType, ValueType};
+use quick_xml::{Reader, XmlVersion, events::Event};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    dbc::decode_text,
+    dbc_editor::{DbcDraft, MessageDraft, SignalDraft},
+    messages::RawCanMessageId,
+};
+
+// Network descriptions that are converted to dbc when loaded
+pub const EXTENSIONS: [&str; 3] = ["kcd", "sym", "arxml"];
+
+// A dbc converted from a network file
+#[derive(Debug)]
+pub struct Converted {
+    pub name: String,
+    pub text: String,
+    // Name of the bus the messages are on, for files that describe several buses
+    pub bus: Option<String>,
+}
+
+// Dbcs with the messages and signals of a KCD, SYM or ARXML file, and what could not be
+// converted. KCD files with several buses give a dbc per bus, since their messages can have the
+// same ids
+pub fn convert(name: &str, bytes: &[u8]) -> Result<(Vec<Converted>, Vec<String>), String> {
+    let text = decode_text(bytes);
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    let mut drafts = vec![(DbcDraft::new(Arc::from(name)), None)];
+    let mut warnings = match extension.as_str() {
+        "kcd" => kcd(&text, &mut drafts),
+        "sym" => sym(&text, &mut drafts[0].0),
+        "arxml" => arxml(&text, &mut drafts[0].0),
+        _ => Err(String::from("it is not a dbc, kcd, sym or arxml file")),
+    }
+    .map_err(|error| format!("{name} could not be read: {error}"))?
+    .into_iter()
+    .map(|skipped| format!("{name}: {skipped}"))
+    .collect::<Vec<_>>();
+    if drafts.len() > 1 {
+        warnings.push(format!(
+            "{name} describes {} buses, each one was loaded as its own dbc",
+            drafts.len()
+        ));
+    }
+
+    let mut converted = Vec::new();
+    for (mut draft, bus) in drafts {
+        let skipped = check(&mut draft)?;
+        warnings.extend(
+            skipped
+                .into_iter()
+                .map(|skipped| format!("{}: {skipped}", draft.name)),
+        );
+        converted.push(Converted {
+            name: draft.name.to_string(),
+            text: draft.to_dbc_text(),
+            bus,
+        });
+    }
+    Ok((converted, warnings))
+}
+
+// Makes the names identifiers and the draft a valid dbc, returning what was changed
+fn check(draft: &mut DbcDraft) -> Result<Vec<String>, String> {
+    for message in &mut draft.messages {
+        message.name = identifier(&message.name);
+        message.transmitter = identifier(&message.transmitter);
+        for signal in &mut message.signals {
+            signal.name = identifier(&signal.name);
+            signal.unit = signal.unit.replace('"', "'");
+            for receiver in &mut signal.receivers {
+                *receiver = identifier(receiver);
+            }
+            for (_, value_name) in &mut signal.values {
+                *value_name = value_name.replace('"', "'");
+            }
+        }
+    }
+    let changed = make_valid(draft);
+    let errors = draft.errors();
+    if !errors.is_empty() {
+        return Err(format!(
+            "{} could not be converted: {}",
+            draft.name,
+            errors.join(", ")
+        ));
+    }
+    Ok(changed)
+}
+
+// Names the other formats allow but dbcs do not, like empty ones or ones that became the same
+// identifier, and messages and signals that can not be in a dbc. Returns what was changed
+fn make_valid(draft: &mut DbcDraft) -> Vec<String> {
+    let mut changed = Vec::new();
+    let mut idx = 0;
+    while idx < draft.messages.len() {
+        let message = &draft.messages[idx];
+        match draft.messages[..idx]
+            .iter()
+            .find(|other| other.id == message.id)
+        {
+            Some(other) => {
+                changed.push(format!(
+                    "{} has the same id as {}, it was skipped",
+                    message.name, other.name
+                ));
+                draft.messages.remove(idx);
+            }
+            None => idx += 1,
+        }
+    }
+
+    let mut message_names = Vec::new();
+    for message in &mut draft.messages {
+        if message.name.is_empty() {
+            message.name = format!("Message_{:X}", message.id.id);
+        }
+        if let Some(name) = unique_name(&message.name, &message_names) {
+            changed.push(format!(
+                "There are two messages called {}, the second one was renamed to {name}",
+                message.name
+            ));
+            message.name = name;
+        }
+        message_names.push(message.name.clone());
+
+        let size = message.size;
+        let message_name = &message.name;
+        message.signals.retain(|signal| {
+            let name = format!("{message_name}.{}", signal.name);
+            if signal.size == 0 || signal.size > 64 {
+                changed.push(format!(
+                    "{name} is {} bits long, it was skipped",
+                    signal.size
+                ));
+                false
+            } else if signal.last_bit() >= size * 8 {
+                changed.push(format!(
+                    "{name} does not fit in the message, it was skipped"
+                ));
+                false
+            } else {
+                true
+            }
+        });
+        let mut signal_names = Vec::new();
+        for signal in &mut message.signals {
+            if signal.name.is_empty() {
+                signal.name = format!("Signal_{}", signal.start_bit);
+            }
+            if let Some(name) = unique_name(&signal.name, &signal_names) {
+                changed.push(format!(
+                    "There are two signals called {}.{}, the second one was renamed to {name}",
+                    message.name, signal.name
+                ));
+                signal.name = name;
+            }
+            signal_names.push(signal.name.clone());
+        }
+    }
+    changed
+}
+
+// The name with a number after it when it is already taken
+fn unique_name(name: &str, taken: &[String]) -> Option<String> {
+    if !taken.iter().any(|other| other == name) {
+        return None;
+    }
+    (2..)
+        .map(|suffix| format!("{name}_{suffix}"))
+        .find(|renamed| !taken.contains(renamed))
+}
+
+// Dbc names can only have letters, digits and underscores
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .trim()
+        .chars()
+        .map(|char| match char.is_ascii_alphanumeric() {
+            true => char,
+            false => '_',
+        })
+        .collect();
+    if identifier.starts_with(|char: char| char.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+// Physical range of every raw value when the file does not give one
+fn fill_range(signal: &mut SignalDraft) {
+    if signal.min != 0.
+        || signal.max != 0.
+        || signal.float != SignalExtendedValueType::SignedOrUnsignedInteger
+    {
+        return;
+    }
+    let size = signal.size.clamp(1, 64) as i32;
+    let (raw_min, raw_max) = match signal.value_type {
+        ValueType::Signed => (-(2f64.powi(size - 1)), 2f64.powi(size - 1) - 1.),
+        ValueType::Unsigned => (0., 2f64.powi(size) - 1.),
+    };
+    let [min, max] = [raw_min, raw_max].map(|raw| raw * signal.factor + signal.offset);
+    signal.min = min.min(max);
+    signal.max = min.max(max);
+}
+
+// Big endian signals numbered from the most significant bit of each byte, like KCD and SYM do,
+// to the dbc numbering
+fn msb_first_bit(bit: u64) -> u64 {
+    bit / 8 * 8 + 7 - bit % 8
+}
+
+// Smallest message that fits the signals
+fn fitting_size(signals: &[SignalDraft]) -> u64 {
+    signals
+        .iter()
+        .map(|signal| signal.last_bit() / 8 + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+fn signal_type(name: &str) -> (ValueType, SignalExtendedValueType) {
+    match name {
+        "signed" => (
+            ValueType::Signed,
+            SignalExtendedValueType::SignedOrUnsignedInteger,
+        ),
+        "float" | "single" => (ValueType::Signed, SignalExtendedValueType::IEEEfloat32Bit),
+        "double" => (ValueType::Signed, SignalExtendedValueType::IEEEdouble64bit),
+        _ => (
+            ValueType::Unsigned,
+            SignalExtendedValueType::SignedOrUnsignedInteger,
+        ),
+    }
+}
+
+// Decimal numbers, or hex ones starting with 0x
+fn parse_number(number: &str) -> Option<u64> {
+    let number = number.trim();
+    match number
+        .strip_prefix("0x")
+        .or_else(|| number.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => number.parse().ok(),
+    }
+}
+
+fn can_id(id: u64, extended: bool) -> Result<RawCanMessageId, String> {
+    let max = if extended { 0x1FFFFFFF } else { 0x7FF };
+    if id > max {
+        return Err(format!("the id {id:X} is too large"));
+    }
+    Ok(RawCanMessageId {
+        id: id as u32,
+        extended,
+    })
+}
+
+// Kayak KCD, an XML format. Every bus goes in its own draft when there are several
+fn kcd(text: &str, drafts: &mut Vec<(DbcDraft, Option<String>)>) -> Result<Vec<String>, String> {
+    let root = parse_xml(text)?;
+    if root.name != "NetworkDefinition" {
+        return Err(String::from("there is no NetworkDefinition"));
+    }
+    let nodes: HashMap<&str, &str> = root
+        .children("Node")
+        .filter_map(|node| Some((node.attribute("id")?, node.attribute("name")?)))
+        .collect();
+    let node_names = |element: &Element, role: &str| -> Vec<String> {
+        element
+            .children(role)
+            .flat_map(|role| role.children("NodeRef"))
+            .filter_map(|node| nodes.get(node.attribute("id")?))
+            .map(|name| name.to_string())
+            .collect()
+    };
+
+    let buses: Vec<&Element> = root.children("Bus").collect();
+    if buses.len() > 1 {
+        let file_name = drafts[0].0.name.clone();
+        let (stem, extension) = file_name.rsplit_once('.').unwrap_or((&file_name, "kcd"));
+        *drafts = buses
+            .iter()
+            .map(|bus| {
+                let bus_name = bus.attribute("name").unwrap_or_default();
+                (
+                    DbcDraft::new(Arc::from(format!("{stem} ({bus_name}).{extension}"))),
+                    Some(bus_name.to_string()),
+                )
+            })
+            .collect();
+    }
+
+    for (idx, bus) in buses.iter().enumerate() {
+        let draft = &mut drafts[idx].0;
+        for message in bus.children("Message") {
+            let name = message.attribute("name").unwrap_or_default();
+            let id = message
+                .attribute("id")
+                .and_then(parse_number)
+                .ok_or_else(|| format!("message {name} has no valid id"))?;
+            let id = can_id(id, message.attribute("format") == Some("extended"))?;
+
+            let mut signals = Vec::new();
+            for signal in message.children("Signal") {
+                signals.push(kcd_signal(signal, MultiplexIndicator::Plain, &node_names)?);
+            }
+            // The multiplexor, with the signals it selects grouped by its value
+            for multiplex in message.children("Multiplex") {
+                signals.push(kcd_signal(
+                    multiplex,
+                    MultiplexIndicator::Multiplexor,
+                    &node_names,
+                )?);
+                for group in multiplex.children("MuxGroup") {
+                    let value = group.attribute("count").and_then(parse_number);
+                    let value =
+                        value.ok_or_else(|| format!("{name} has a MuxGroup without count"))?;
+                    for signal in group.children("Signal") {
+                        signals.push(kcd_signal(
+                            signal,
+                            MultiplexIndicator::MultiplexedSignal(value),
+                            &node_names,
+                        )?);
+                    }
+                }
+            }
+
+            let size = match message.attribute("length").and_then(parse_number) {
+                Some(size) => size,
+                // "auto" or missing
+                None => fitting_size(&signals),
+            };
+            draft.messages.push(MessageDraft {
+                id,
+                name: name.to_string(),
+                size,
+                transmitter: node_names(message, "Producer")
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+                signals,
+            });
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn kcd_signal(
+    element: &Element,
+    multiplexer: MultiplexIndicator,
+    node_names: &impl Fn(&Element, &str) -> Vec<String>,
+) -> Result<SignalDraft, String> {
+    let name = element.attribute("name").unwrap_or_default();
+    let offset = element
+        .attribute("offset")
+        .and_then(parse_number)
+        .ok_or_else(|| format!("signal {name} has no valid offset"))?;
+    let size = element
+        .attribute("length")
+        .and_then(parse_number)
+        .unwrap_or(1);
+    let big_endian = element.attribute("endianess") == Some("big");
+    let value = element.child("Value");
+    let value_attribute = |attribute: &str| value.and_then(|value| value.attribute(attribute));
+    let number =
+        |attribute: &str| value_attribute(attribute).and_then(|number| number.parse().ok());
+    let (value_type, float) = signal_type(value_attribute("type").unwrap_or("unsigned"));
+
+    let mut values = Vec::new();
+    for label_set in element.children("LabelSet") {
+        for label in label_set.children("Label") {
+            if let (Some(value), Some(name)) = (
+                label.attribute("value").and_then(parse_number),
+                label.attribute("name"),
+            ) {
+                values.push((value as f64, name.to_string()));
+            }
+        }
+    }
+
+    let mut signal = SignalDraft {
+        multiplexer,
+        start_bit: if big_endian {
+            msb_first_bit(offset)
+        } else {
+            offset
+        },
+        size,
+        byte_order: if big_endian {
+            ByteOrder::BigEndian
+        } else {
+            ByteOrder::LittleEndian
+        },
+        value_type,
+        float,
+        factor: number("slope").unwrap_or(1.),
+        offset: number("intercept").unwrap_or(0.),
+        min: number("min").unwrap_or(0.),
+        max: number("max").unwrap_or(0.),
+        unit: value_attribute("unit").unwrap_or_default().to_string(),
+        receivers: node_names(element, "Consumer"),
+        values,
+        ..SignalDraft::new(name.to_string())
+    };
+    fill_range(&mut signal);
+    Ok(signal)
+}
+
+// PCAN Symbol Editor SYM, an ini like format
+fn sym(text: &str, draft: &mut DbcDraft) -> Result<Vec<String>, String> {
+    let mut skipped = Vec::new();
+    let mut enums: HashMap<String, Vec<(f64, String)>> = HashMap::new();
+    // Signals defined once in {SIGNALS} and placed in messages with Sig=
+    let mut definitions: HashMap<String, SignalDraft> = HashMap::new();
+    // [Name] blocks with their key=value lines, read once everything else is known
+    let mut blocks: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    let mut section = String::new();
+
+    let mut lines = text.lines().map(strip_sym_comment);
+    while let Some(line) = lines.next() {
+        let mut line = line.trim().to_string();
+        if let Some(name) = line
+            .strip_prefix('{')
+            .and_then(|line| line.strip_suffix('}'))
+        {
+            section = name.to_string();
+        } else if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            blocks.push((name.to_string(), Vec::new()));
+        } else if section == "ENUMS" && line.starts_with("enum ") {
+            // Long enums go on over several lines
+            while !line.contains(')') {
+                let Some(next) = lines.next() else { break };
+                line.push_str(next.trim());
+            }
+            let (name, values) =
+                parse_sym_enum(&line).ok_or_else(|| format!("the enum {line} is not valid"))?;
+            enums.insert(name, values);
+        } else if let Some((key, value)) = line.split_once('=') {
+            let (key, value) = (key.trim().to_string(), value.trim().to_string());
+            if let Some((_, entries)) = blocks.last_mut()
+                && section != "SIGNALS"
+            {
+                entries.push((key, value));
+            } else if section == "SIGNALS" && key == "Sig" {
+                let tokens = sym_tokens(&value);
+                let [name, kind, length, flags @ ..] = tokens.as_slice() else {
+                    return Err(format!("the signal {value} is not valid"));
+                };
+                let size = length
+                    .parse()
+                    .map_err(|_| format!("the signal {name} has no valid length"))?;
+                match sym_signal(name, kind, 0, size, flags, &enums) {
+                    Ok(signal) => {
+                        definitions.insert(name.clone(), signal);
+                    }
+                    Err(reason) => skipped.push(format!("{name} {reason}, it was skipped")),
+                }
+            }
+        }
+    }
+
+    for (block_name, entries) in &blocks {
+        let entry = |key: &str| {
+            entries
+                .iter()
+                .find(|(entry, _)| entry == key)
+                .map(|(_, value)| value.as_str())
+        };
+        // Ranges of ids only keep their first one
+        let id = entry("ID")
+            .and_then(|id| id.split('-').next())
+            .and_then(parse_sym_number)
+            .ok_or_else(|| format!("{block_name} has no valid id"))?;
+        let extended = entry("Type").is_some_and(|kind| kind.eq_ignore_ascii_case("extended"));
+        let id = can_id(id, extended)?;
+        let size = entry("DLC")
+            .or_else(|| entry("Len"))
+            .and_then(|size| size.parse().ok());
+
+        // Multiplexed messages are one block per multiplexor value with the same id
+        let message_idx = match draft.messages.iter().position(|message| message.id == id) {
+            Some(idx) => idx,
+            None => {
+                draft.messages.push(MessageDraft {
+                    id,
+                    name: block_name.clone(),
+                    size: 0,
+                    transmitter: String::new(),
+                    signals: Vec::new(),
+                });
+                draft.messages.len() - 1
+            }
+        };
+        let message = &mut draft.messages[message_idx];
+        message.size = message.size.max(size.unwrap_or_default());
+
+        let mut mux = None;
+        for (key, value) in entries {
+            let tokens = sym_tokens(value);
+            let signal = match key.as_str() {
+                "Var" => {
+                    let [name, kind, position, flags @ ..] = tokens.as_slice() else {
+                        return Err(format!("the signal {value} is not valid"));
+                    };
+                    let (start, size) = parse_sym_position(position)
+                        .ok_or_else(|| format!("{name} has no valid position"))?;
+                    sym_signal(name, kind, start, size, flags, &enums)
+                }
+                "Mux" => {
+                    let [name, position, value, flags @ ..] = tokens.as_slice() else {
+                        return Err(format!("the multiplexor {value} is not valid"));
+                    };
+                    let (start, size) = parse_sym_position(position)
+                        .ok_or_else(|| format!("{name} has no valid position"))?;
+                    mux = Some(
+                        parse_sym_number(value)
+                            .ok_or_else(|| format!("{name} has no valid value"))?,
+                    );
+                    sym_signal(name, "unsigned", start, size, flags, &enums).map(|signal| {
+                        SignalDraft {
+                            multiplexer: MultiplexIndicator::Multiplexor,
+                            ..signal
+                        }
+                    })
+                }
+                "Sig" => {
+                    let [name, start, flags @ ..] = tokens.as_slice() else {
+                        return Err(format!("the signal {value} is not valid"));
+                    };
+                    let definition = definitions
+                        .get(name)
+                        .ok_or_else(|| format!("the signal {name} is not defined"))?;
+                    let start = start
+                        .parse()
+                        .map_err(|_| format!("{name} has no valid start bit"))?;
+                    let big_endian = definition.byte_order == ByteOrder::BigEndian
+                        || flags.iter().any(|flag| flag == "-m");
+                    Ok(SignalDraft {
+                        start_bit: if big_endian {
+                            msb_first_bit(start)
+                        } else {
+                            start
+                        },
+                        byte_order: if big_endian {
+                            ByteOrder::BigEndian
+                        } else {
+                            ByteOrder::LittleEndian
+                        },
+                        ..definition.clone()
+                    })
+                }
+                _ => continue,
+            };
+
+            let mut signal = match signal {
+                Ok(signal) => signal,
+                Err(reason) => {
+                    skipped.push(format!(
+                        "{block_name}.{} {reason}, it was skipped",
+                        tokens[0]
+                    ));
+                    continue;
+                }
+            };
+            if signal.multiplexer == MultiplexIndicator::Plain
+                && let Some(value) = mux
+            {
+                signal.multiplexer = MultiplexIndicator::MultiplexedSignal(value);
+            }
+            // Every block of a multiplexed message repeats the multiplexor
+            if !message
+                .signals
+                .iter()
+                .any(|other| other.name == signal.name)
+            {
+                message.signals.push(signal);
+            }
+        }
+    }
+
+    for message in &mut draft.messages {
+        message.size = message.size.max(fitting_size(&message.signals));
+    }
+    Ok(skipped)
+}
+
+// Removes // comments that are not inside quotes
+fn strip_sym_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut previous = ' ';
+    for (idx, char) in line.char_indices() {
+        match char {
+            '"' => quoted = !quoted,
+            '/' if previous == '/' && !quoted => return &line[..idx - 1],
+            _ => {}
+        }
+        previous = char;
+    }
+    line
+}
+
+// Words separated by spaces, keeping quoted text together
+fn sym_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for char in line.chars() {
+        match char {
+            '"' => quoted = !quoted,
+            char if char.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            char => token.push(char),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+// Decimal numbers, or hex ones ending in h
+fn parse_sym_number(number: &str) -> Option<u64> {
+    let number = number.trim();
+    match number
+        .strip_suffix('h')
+        .or_else(|| number.strip_suffix('H'))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => number.parse().ok(),
+    }
+}
+
+// start,length
+fn parse_sym_position(position: &str) -> Option<(u64, u64)> {
+    let (start, size) = position.split_once(',')?;
+    Some((start.parse().ok()?, size.parse().ok()?))
+}
+
+// enum Name(0="First", 1="Second")
+fn parse_sym_enum(line: &str) -> Option<(String, Vec<(f64, String)>)> {
+    let (name, values) = line.strip_prefix("enum ")?.split_once('(')?;
+    let values = values.rsplit_once(')')?.0;
+    let mut parsed = Vec::new();
+    let mut quoted = false;
+    let mut entry = String::new();
+    for char in values.chars().chain([',']) {
+        match char {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                if let Some((value, name)) = entry.split_once('=') {
+                    parsed.push((parse_sym_number(value)? as f64, name.trim().to_string()));
+                }
+                entry.clear();
+            }
+            char => entry.push(char),
+        }
+    }
+    Some((name.trim().to_string(), parsed))
+}
+
+// Why the signal can not be in a dbc for strings, other signals that are not numbers and
+// signals that are not 1 to 64 bits long
+fn sym_signal(
+    name: &str,
+    kind: &str,
+    start: u64,
+    size: u64,
+    flags: &[String],
+    enums: &HashMap<String, Vec<(f64, String)>>,
+) -> Result<SignalDraft, String> {
+    let (value_type, float) = match kind {
+        "bit" | "char" | "unsigned" => signal_type("unsigned"),
+        "signed" | "float" | "double" => signal_type(kind),
+        _ => return Err(String::from("is not a number signal")),
+    };
+    if size == 0 || size > 64 {
+        return Err(format!("is {size} bits long, dbc signals are 1 to 64 bits"));
+    }
+    let big_endian = flags.iter().any(|flag| flag == "-m");
+    let mut signal = SignalDraft {
+        start_bit: if big_endian {
+            msb_first_bit(start)
+        } else {
+            start
+        },
+        size,
+        byte_order: if big_endian {
+            ByteOrder::BigEndian
+        } else {
+            ByteOrder::LittleEndian
+        },
+        value_type,
+        float,
+        min: 0.,
+        max: 0.,
+        ..SignalDraft::new(name.to_string())
+    };
+    for flag in flags {
+        let Some((key, value)) = flag.strip_prefix('/').and_then(|flag| flag.split_once(':'))
+        else {
+            continue;
+        };
+        let number = value.parse();
+        match key {
+            "u" => signal.unit = value.to_string(),
+            "f" => signal.factor = number.unwrap_or(1.),
+            "o" => signal.offset = number.unwrap_or(0.),
+            "min" => signal.min = number.unwrap_or(0.),
+            "max" => signal.max = number.unwrap_or(0.),
+            "e" => signal.values = enums.get(value).cloned().unwrap_or_default(),
+            _ => {}
+        }
+    }
+    fill_range(&mut signal);
+    Ok(signal)
+}
+
+// AUTOSAR 4 ARXML, only its CAN frames with plain signal PDUs
+fn arxml(text: &str, draft: &mut DbcDraft) -> Result<Vec<String>, String> {
+    let root = parse_xml(text)?;
+    let mut paths = HashMap::new();
+    index_paths(&root, String::new(), &mut paths);
+    let resolve = |element: &Element, reference: &str| -> Option<&Element> {
+        let path = element.descendants(reference).first()?.text.trim();
+        paths.get(path).copied()
+    };
+
+    let triggerings = root.descendants("CAN-FRAME-TRIGGERING");
+    if triggerings.is_empty() {
+        return Err(String::from("there are no CAN frames in it"));
+    }
+    let mut skipped = Vec::new();
+    for triggering in triggerings {
+        let Some(frame) = resolve(triggering, "FRAME-REF") else {
+            continue;
+        };
+        let name = frame.child_text("SHORT-NAME").unwrap_or_default();
+        let id = triggering
+            .child_text("IDENTIFIER")
+            .and_then(parse_number)
+            .ok_or_else(|| format!("frame {name} has no valid id"))?;
+        let extended = triggering.child_text("CAN-ADDRESSING-MODE") == Some("EXTENDED");
+        let id = can_id(id, extended)?;
+        // The same frame can be sent on several channels
+        if let Some(message) = draft.messages.iter().find(|message| message.id == id) {
+            if message.name != name {
+                skipped.push(format!(
+                    "{name} has the same id as {}, it was skipped",
+                    message.name
+                ));
+            }
+            continue;
+        }
+
+        let mut signals = Vec::new();
+        for pdu_mapping in frame.descendants("PDU-TO-FRAME-MAPPING") {
+            let Some(pdu) = resolve(pdu_mapping, "PDU-REF") else {
+                continue;
+            };
+            if pdu.name != "I-SIGNAL-I-PDU" {
+                skipped.push(format!(
+                    "{name} has a {} that was skipped, only I-SIGNAL-I-PDUs are supported",
+                    pdu.name
+                ));
+                continue;
+            }
+            let pdu_start = pdu_mapping
+                .child_text("START-POSITION")
+                .and_then(parse_number)
+                .unwrap_or_default();
+            for mapping in pdu.descendants("I-SIGNAL-TO-I-PDU-MAPPING") {
+                let Some(i_signal) = resolve(mapping, "I-SIGNAL-REF") else {
+                    continue;
+                };
+                match arxml_signal(mapping, i_signal, pdu_start, &resolve) {
+                    Some(signal) => signals.push(signal),
+                    None => skipped.push(format!(
+                        "{name}.{} was skipped, its position could not be read",
+                        i_signal.child_text("SHORT-NAME").unwrap_or_default()
+                    )),
+                }
+            }
+        }
+
+        draft.messages.push(MessageDraft {
+            id,
+            name: name.to_string(),
+            size: frame
+                .child_text("FRAME-LENGTH")
+                .and_then(parse_number)
+                .unwrap_or_else(|| fitting_size(&signals)),
+            transmitter: String::new(),
+            signals,
+        });
+    }
+    Ok(skipped)
+}
+
+fn arxml_signal<'a>(
+    mapping: &Element,
+    i_signal: &'a Element,
+    pdu_start: u64,
+    resolve: &impl Fn(&Element, &str) -> Option<&'a Element>,
+) -> Option<SignalDraft> {
+    let size = i_signal.child_text("LENGTH").and_then(parse_number)?;
+    let big_endian =
+        mapping.child_text("PACKING-BYTE-ORDER") == Some("MOST-SIGNIFICANT-BYTE-FIRST");
+    let start = mapping
+        .child_text("START-POSITION")
+        .and_then(parse_number)?
+        + pdu_start;
+    let start_bit = if big_endian {
+        // AUTOSAR gives the least significant bit of big endian signals, dbcs the most significant
+        let mut bit = start;
+        for _ in 1..size {
+            bit = if bit % 8 == 7 {
+                bit.checked_sub(15)?
+            } else {
+                bit + 1
+            };
+        }
+        bit
+    } else {
+        start
+    };
+
+    let system_signal = resolve(i_signal, "SYSTEM-SIGNAL-REF");
+    let compu_method = resolve(i_signal, "COMPU-METHOD-REF")
+        .or_else(|| system_signal.and_then(|signal| resolve(signal, "COMPU-METHOD-REF")));
+    let (value_type, float) = match resolve(i_signal, "BASE-TYPE-REF") {
+        Some(base_type) => match base_type.child_text("BASE-TYPE-ENCODING") {
+            Some("2C") => signal_type("signed"),
+            Some("IEEE754") if size == 64 => signal_type("double"),
+            Some("IEEE754") => signal_type("float"),
+            _ => signal_type("unsigned"),
+        },
+        None => signal_type("unsigned"),
+    };
+
+    let mut signal = SignalDraft {
+        start_bit,
+        size,
+        byte_order: if big_endian {
+            ByteOrder::BigEndian
+        } else {
+            ByteOrder::LittleEndian
+        },
+        value_type,
+        float,
+        min: 0.,
+        max: 0.,
+        ..SignalDraft::new(
+            i_signal
+                .child_text("SHORT-NAME")
+                .unwrap_or_default()
+                .to_string(),
+        )
+    };
+    if let Some(compu_method) = compu_method {
+        signal.unit = resolve(compu_method, "UNIT-REF")
+            .and_then(|unit| {
+                unit.child_text("DISPLAY-NAME")
+                    .or_else(|| unit.child_text("SHORT-NAME"))
+            })
+            .unwrap_or_default()
+            .to_string();
+        arxml_scales(compu_method, &mut signal);
+    }
+    fill_range(&mut signal);
+    Some(signal)
+}
+
+// Factor and offset from the linear scale, and value names from the single value ones
+fn arxml_scales(compu_method: &Element, signal: &mut SignalDraft) {
+    let Some(to_physical) = compu_method.child("COMPU-INTERNAL-TO-PHYS") else {
+        return;
+    };
+    let limit =
+        |scale: &Element, limit: &str| -> Option<f64> { scale.child_text(limit)?.parse().ok() };
+    for scale in to_physical.descendants("COMPU-SCALE") {
+        let lower = limit(scale, "LOWER-LIMIT");
+        let upper = limit(scale, "UPPER-LIMIT");
+        if let Some(name) = scale.descendants("VT").first() {
+            if let Some(lower) = lower.filter(|lower| Some(*lower) == upper) {
+                signal.values.push((lower, name.text.trim().to_string()));
+            }
+            continue;
+        }
+        let Some(coefficients) = scale.child("COMPU-RATIONAL-COEFFS") else {
+            continue;
+        };
+        let values = |part: &str| -> Vec<f64> {
+            coefficients
+                .child(part)
+                .map(|part| {
+                    part.children("V")
+                        .filter_map(|value| value.text.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let numerator = values("COMPU-NUMERATOR");
+        let denominator = values("COMPU-DENOMINATOR").first().copied().unwrap_or(1.);
+        signal.offset = numerator.first().copied().unwrap_or(0.) / denominator;
+        signal.factor = numerator.get(1).copied().unwrap_or(1.) / denominator;
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            let [min, max] = [lower, upper].map(|raw| raw * signal.factor + signal.offset);
+            signal.min = min.min(max);
+            signal.max = min.max(max);
+        }
+    }
+}
+
+// Path of every element with a SHORT-NAME, which is how ARXML references them
+fn index_paths<'a>(
+    element: &'a Element,
+    mut path: String,
+    paths: &mut HashMap<String, &'a Element>,
+) {
+    if let Some(name) = element.child_text("SHORT-NAME") {
+        path = format!("{path}/{name}");
+        paths.insert(path.clone(), element);
+    }
+    for child in &element.children {
+        index_paths(child, path.clone(), paths);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    // Without the namespace
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn child_text(&self, name: &str) -> Option<&str> {
+        Some(self.child(name)?.text.trim())
+    }
+
+    // Every element with that name inside this one, at any depth
+    fn descendants(&self, name: &str) -> Vec<&Element> {
+        let mut found = Vec::new();
+        let mut stack: Vec<&Element> = self.children.iter().rev().collect();
+        while let Some(element) = stack.pop() {
+            if element.name == name {
+                found.push(element);
+            }
+            stack.extend(element.children.iter().rev());
+        }
+        found
+    }
+}
+
+// The root element of an XML document
+fn parse_xml(text: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(text);
+    // The document itself, holding the root element
+    let mut stack = vec![Element::default()];
+    let new_element = |start: &quick_xml::events::BytesStart| -> Result<Element, String> {
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|error| error.to_string())?;
+            let value = attribute
+                .normalized_value(XmlVersion::Implicit1_0)
+                .map_err(|error| error.to_string())?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                value.into_owned(),
+            ));
+        }
+        Ok(Element {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+            attributes,
+            ..Default::default()
+        })
+    };
+
+    loop {
+        let event = reader.read_event().map_err(|error| {
+            let position = (reader.error_position() as usize).min(text.len());
+            let line = text.as_bytes()[..position]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count()
+                + 1;
+            format!("it is not valid XML, line {line}: {error}")
+        })?;
+        let parent = stack.last_mut().expect("The document is never popped");
+        match event {
+            Event::Start(start) => {
+                let element = new_element(&start)?;
+                stack.push(element);
+            }
+            Event::Empty(start) => parent.children.push(new_element(&start)?),
+            Event::End(_) => {
+                let element = stack.pop().expect("Ends match their starts");
+                stack
+                    .last_mut()
+                    .ok_or("it is not valid XML")?
+                    .children
+                    .push(element);
+            }
+            Event::Text(text) => parent
+                .text
+                .push_str(&text.decode().map_err(|error| error.to_string())?),
+            Event::CData(data) => parent
+                .text
+                .push_str(&data.decode().map_err(|error| error.to_string())?),
+            Event::GeneralRef(reference) => {
+                let name = reference.decode().map_err(|error| error.to_string())?;
+                match reference.resolve_char_ref().ok().flatten() {
+                    Some(char) => parent.text.push(char),
+                    None => parent.text.push_str(
+                        quick_xml::escape::resolve_predefined_entity(&name).unwrap_or_default(),
+                    ),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let document = stack.swap_remove(0);
+    document
+        .children
+        .into_iter()
+        .next()
+        .ok_or_else(|| String::from("it is an empty XML document"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbc::Dbc;
+
+    // Messages of the converted file with their signals as name, start bit, size, big endian,
+    // factor, offset, unit and value names
+    fn signals(name: &str, text: &str) -> Vec<(String, Vec<String>)> {
+        let (converted, _) = convert(name, text.as_bytes()).unwrap();
+        assert_eq!(converted.len(), 1);
+        let dbc = Dbc::new(Arc::from(name), Arc::from(converted[0].text.as_bytes())).unwrap();
+        assert_eq!(dbc.warning, None);
+        dbc.inner
+            .messages()
+            .iter()
+            .map(|message| {
+                let signals = message
+                    .signals()
+                    .iter()
+                    .map(|signal| {
+                        let values = dbc
+                            .inner
+                            .value_descriptions_for_signal(*message.message_id(), signal.name())
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|value| format!("{}={}", value.a(), value.b()))
+                            .collect::<Vec<_>>();
+                        format!(
+                            "{} {}|{}@{} ({},{}) {:?} {:?} [{}]",
+                            signal.name(),
+                            signal.start_bit(),
+                            signal.signal_size(),
+                            match signal.byte_order() {
+                                ByteOrder::LittleEndian => "LE",
+                                ByteOrder::BigEndian => "BE",
+                            },
+                            signal.factor(),
+                            signal.offset(),
+                            signal.unit(),
+                            signal.multiplexer_indicator(),
+                            values.join(", ")
+                        )
+                    })
+                    .collect();
+                (
+                    format!(
+                        "{} {} {}",
+                        RawCanMessageId::from(*message.message_id()),
+                        message.message_name(),
+                        message.message_size()
+                    ),
+                    signals,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kcd() {
+        let kcd = r#"<?xml version="1.0" encoding="UTF-8"?>
+<NetworkDefinition xmlns="http://kayak.2codeornot2code.org/1.0">
+  <Node id="1" name="Engine ECU"/>
+  <Bus name="Powertrain">
+    <Message id="0x100" name="Engine" length="8">
+      <Producer><NodeRef id="1"/></Producer>
+      <Signal name="Speed" offset="0" length="16">
+        <Value slope="0.25" unit="rpm"/>
+      </Signal>
+      <Signal name="Gear" offset="16" length="4">
+        <LabelSet><Label value="0" name="Park &amp; hold"/><Label value="1" name="Drive"/></LabelSet>
+      </Signal>
+      <Multiplex name="Page" offset="24" length="8">
+        <MuxGroup count="2">
+          <Signal name="Temperature" offset="32" length="16" endianess="big">
+            <Value type="signed" intercept="-40"/>
+          </Signal>
+        </MuxGroup>
+      </Multiplex>
+    </Message>
+  </Bus>
+</NetworkDefinition>"#;
+        assert_eq!(
+            signals("car.kcd", kcd),
+            [(
+                String::from("0x100 Engine 8"),
+                vec![
+                    String::from("Speed 0|16@LE (0.25,0) \"rpm\" Plain []"),
+                    String::from("Gear 16|4@LE (1,0) \"\" Plain [0=Park & hold, 1=Drive]"),
+                    String::from("Page 24|8@LE (1,0) \"\" Multiplexor []"),
+                    String::from("Temperature 39|16@BE (1,-40) \"\" MultiplexedSignal(2) []"),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_parts_are_renamed_or_skipped() {
+        let kcd = r#"<NetworkDefinition>
+  <Bus name="A">
+    <Message id="0x100" name="a b" length="2">
+      <Signal name="x" offset="0" length="8"/>
+      <Signal name="x" offset="8" length="8"/>
+      <Signal name="Too long" offset="8" length="16"/>
+    </Message>
+    <Message id="0x101" name="a_b"/>
+    <Message id="0x100" name="Other"/>
+  </Bus>
+</NetworkDefinition>"#;
+        let (converted, warnings) = convert("car.kcd", kcd.as_bytes()).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "car.kcd: Other has the same id as a_b, it was skipped",
+                "car.kcd: a_b.Too_long does not fit in the message, it was skipped",
+                "car.kcd: There are two signals called a_b.x, the second one was renamed to x_2",
+                "car.kcd: There are two messages called a_b, the second one was renamed to a_b_2",
+            ]
+        );
+        let text = converted[0].text.as_bytes();
+        let dbc = Dbc::new(Arc::from("car.dbc"), Arc::from(text)).unwrap();
+        assert_eq!(dbc.inner.messages().len(), 2);
+    }
+
+    #[test]
+    fn kcd_buses_are_dbcs_of_their_own() {
+        let kcd = r#"<NetworkDefinition>
+  <Bus name="can0">
+    <Message id="0x100" name="Engine"><Signal name="Speed" offset="0" length="8"/></Message>
+  </Bus>
+  <Bus name="can1">
+    <Message id="0x100" name="Brake"><Signal name="Pressure" offset="0" length="8"/></Message>
+  </Bus>
+</NetworkDefinition>"#;
+        let (converted, warnings) = convert("car.kcd", kcd.as_bytes()).unwrap();
+        assert_eq!(
+            warnings,
+            ["car.kcd describes 2 buses, each one was loaded as its own dbc"]
+        );
+        let dbcs: Vec<(&str, Option<&str>, String)> = converted
+            .iter()
+            .map(|converted| {
+                let dbc = Dbc::new(
+                    Arc::from(converted.name.as_str()),
+                    Arc::from(converted.text.as_bytes()),
+                )
+                .unwrap();
+                let message = dbc.inner.messages()[0].message_name().clone();
+                (converted.name.as_str(), converted.bus.as_deref(), message)
+            })
+            .collect();
+        assert_eq!(
+            dbcs,
+            [
+                ("car (can0).kcd", Some("can0"), String::from("Engine")),
+                ("car (can1).kcd", Some("can1"), String::from("Brake")),
+            ]
+        );
+    }
+
+    #[test]
+    fn sym() {
+        let sym = r#"FormatVersion=6.0 // Do not edit this line!
+Title="Car"
+
+{ENUMS}
+enum Gears(0="Park", 1="Reverse",
+  2="Neutral, idle")
+
+{SIGNALS}
+Sig=Voltage unsigned 16 -m /u:V /f:0.01
+
+{SENDRECEIVE}
+
+[Engine]
+ID=18FF0001h
+Type=Extended
+DLC=8
+Var=Speed unsigned 0,16 /u:"km/h" /f:0.1
+Var=Gear unsigned 16,4 /e:Gears
+Var=Name string 24,40
+Var=Counter unsigned 0,0
+Sig=Voltage 40
+
+[Status]
+ID=200h
+DLC=4
+Mux=Page 0,8 1
+Var=Temperature signed 8,8 /o:-40
+[Status]
+ID=200h
+DLC=4
+Mux=Page 0,8 2
+Var=Pressure unsigned 8,16
+"#;
+        let (_, warnings) = convert("car.sym", sym.as_bytes()).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "car.sym: Engine.Name is not a number signal, it was skipped",
+                "car.sym: Engine.Counter is 0 bits long, dbc signals are 1 to 64 bits, it was skipped",
+            ]
+        );
+        assert_eq!(
+            signals("car.sym", sym),
+            [
+                (
+                    String::from("0x18FF0001 Engine 8"),
+                    vec![
+                        String::from("Speed 0|16@LE (0.1,0) \"km/h\" Plain []"),
+                        String::from(
+                            "Gear 16|4@LE (1,0) \"\" Plain [0=Park, 1=Reverse, 2=Neutral, idle]"
+                        ),
+                        String::from("Voltage 47|16@BE (0.01,0) \"V\" Plain []"),
+                    ]
+                ),
+                (
+                    String::from("0x200 Status 4"),
+                    vec![
+                        String::from("Page 0|8@LE (1,0) \"\" Multiplexor []"),
+                        String::from("Temperature 8|8@LE (1,-40) \"\" MultiplexedSignal(1) []"),
+                        String::from("Pressure 8|16@LE (1,0) \"\" MultiplexedSignal(2) []"),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn arxml() {
+        let arxml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AUTOSAR xmlns="http://autosar.org/schema/r4.0">
+  <AR-PACKAGES>
+    <AR-PACKAGE>
+      <SHORT-NAME>Car</SHORT-NAME>
+      <ELEMENTS>
+        <CAN-CLUSTER>
+          <SHORT-NAME>Powertrain</SHORT-NAME>
+          <CAN-CLUSTER-VARIANTS><CAN-CLUSTER-CONDITIONAL><PHYSICAL-CHANNELS>
+            <CAN-PHYSICAL-CHANNEL>
+              <SHORT-NAME>Channel</SHORT-NAME>
+              <FRAME-TRIGGERINGS>
+                <CAN-FRAME-TRIGGERING>
+                  <SHORT-NAME>EngineTriggering</SHORT-NAME>
+                  <FRAME-REF DEST="CAN-FRAME">/Car/Engine</FRAME-REF>
+                  <CAN-ADDRESSING-MODE>STANDARD</CAN-ADDRESSING-MODE>
+                  <IDENTIFIER>256</IDENTIFIER>
+                </CAN-FRAME-TRIGGERING>
+              </FRAME-TRIGGERINGS>
+            </CAN-PHYSICAL-CHANNEL>
+          </PHYSICAL-CHANNELS></CAN-CLUSTER-CONDITIONAL></CAN-CLUSTER-VARIANTS>
+        </CAN-CLUSTER>
+        <CAN-FRAME>
+          <SHORT-NAME>Engine</SHORT-NAME>
+          <FRAME-LENGTH>8</FRAME-LENGTH>
+          <PDU-TO-FRAME-MAPPINGS>
+            <PDU-TO-FRAME-MAPPING>
+              <SHORT-NAME>EnginePdu</SHORT-NAME>
+              <PDU-REF DEST="I-SIGNAL-I-PDU">/Car/EnginePdu</PDU-REF>
+              <START-POSITION>0</START-POSITION>
+            </PDU-TO-FRAME-MAPPING>
+          </PDU-TO-FRAME-MAPPINGS>
+        </CAN-FRAME>
+        <I-SIGNAL-I-PDU>
+          <SHORT-NAME>EnginePdu</SHORT-NAME>
+          <LENGTH>8</LENGTH>
+          <I-SIGNAL-TO-PDU-MAPPINGS>
+            <I-SIGNAL-TO-I-PDU-MAPPING>
+              <SHORT-NAME>Speed</SHORT-NAME>
+              <I-SIGNAL-REF DEST="I-SIGNAL">/Car/Speed</I-SIGNAL-REF>
+              <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-LAST</PACKING-BYTE-ORDER>
+              <START-POSITION>0</START-POSITION>
+            </I-SIGNAL-TO-I-PDU-MAPPING>
+            <I-SIGNAL-TO-I-PDU-MAPPING>
+              <SHORT-NAME>Gear</SHORT-NAME>
+              <I-SIGNAL-REF DEST="I-SIGNAL">/Car/Gear</I-SIGNAL-REF>
+              <PACKING-BYTE-ORDER>MOST-SIGNIFICANT-BYTE-FIRST</PACKING-BYTE-ORDER>
+              <START-POSITION>24</START-POSITION>
+            </I-SIGNAL-TO-I-PDU-MAPPING>
+          </I-SIGNAL-TO-PDU-MAPPINGS>
+        </I-SIGNAL-I-PDU>
+        <I-SIGNAL>
+          <SHORT-NAME>Speed</SHORT-NAME>
+          <LENGTH>16</LENGTH>
+          <SYSTEM-SIGNAL-REF DEST="SYSTEM-SIGNAL">/Car/SpeedSystem</SYSTEM-SIGNAL-REF>
+        </I-SIGNAL>
+        <SYSTEM-SIGNAL>
+          <SHORT-NAME>SpeedSystem</SHORT-NAME>
+          <PHYSICAL-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+            <COMPU-METHOD-REF DEST="COMPU-METHOD">/Car/SpeedMethod</COMPU-METHOD-REF>
+          </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></PHYSICAL-PROPS>
+        </SYSTEM-SIGNAL>
+        <COMPU-METHOD>
+          <SHORT-NAME>SpeedMethod</SHORT-NAME>
+          <UNIT-REF DEST="UNIT">/Car/Kmh</UNIT-REF>
+          <COMPU-INTERNAL-TO-PHYS><COMPU-SCALES><COMPU-SCALE>
+            <LOWER-LIMIT>0</LOWER-LIMIT>
+            <UPPER-LIMIT>65535</UPPER-LIMIT>
+            <COMPU-RATIONAL-COEFFS>
+              <COMPU-NUMERATOR><V>-10</V><V>1</V></COMPU-NUMERATOR>
+              <COMPU-DENOMINATOR><V>10</V></COMPU-DENOMINATOR>
+            </COMPU-RATIONAL-COEFFS>
+          </COMPU-SCALE></COMPU-SCALES></COMPU-INTERNAL-TO-PHYS>
+        </COMPU-METHOD>
+        <UNIT>
+          <SHORT-NAME>Kmh</SHORT-NAME>
+          <DISPLAY-NAME>km/h</DISPLAY-NAME>
+        </UNIT>
+        <I-SIGNAL>
+          <SHORT-NAME>Gear</SHORT-NAME>
+          <LENGTH>12</LENGTH>
+          <NETWORK-REPRESENTATION-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+            <COMPU-METHOD-REF DEST="COMPU-METHOD">/Car/GearMethod</COMPU-METHOD-REF>
+          </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></NETWORK-REPRESENTATION-PROPS>
+        </I-SIGNAL>
+        <COMPU-METHOD>
+          <SHORT-NAME>GearMethod</SHORT-NAME>
+          <COMPU-INTERNAL-TO-PHYS><COMPU-SCALES>
+            <COMPU-SCALE>
+              <LOWER-LIMIT>0</LOWER-LIMIT><UPPER-LIMIT>0</UPPER-LIMIT>
+              <COMPU-CONST><VT>Park</VT></COMPU-CONST>
+            </COMPU-SCALE>
+            <COMPU-SCALE>
+              <LOWER-LIMIT>1</LOWER-LIMIT><UPPER-LIMIT>1</UPPER-LIMIT>
+              <COMPU-CONST><VT>Drive</VT></COMPU-CONST>
+            </COMPU-SCALE>
+          </COMPU-SCALES></COMPU-INTERNAL-TO-PHYS>
+        </COMPU-METHOD>
+      </ELEMENTS>
+    </AR-PACKAGE>
+  </AR-PACKAGES>
+</AUTOSAR>"#;
+        assert_eq!(
+            signals("car.arxml", arxml),
+            [(
+                String::from("0x100 Engine 8"),
+                vec![
+                    String::from("Speed 0|16@LE (0.1,-1) \"km/h\" Plain []"),
+                    // All of byte 3 and bits 16 to 19, the most significant one is 19
+                    String::from("Gear 19|12@BE (1,0) \"\" Plain [0=Park, 1=Drive]"),
+                ]
+            )]
+        );
+
+        // Another frame with the same id is skipped, the same frame on another channel is not
+        // worth a warning
+        let triggering = "<CAN-FRAME-TRIGGERING>\n                  <SHORT-NAME>EngineTriggering";
+        let brake = arxml
+            .replacen(
+                triggering,
+                &format!(
+                    "<CAN-FRAME-TRIGGERING><FRAME-REF DEST=\"CAN-FRAME\">/Car/Engine</FRAME-REF>\
+                 <IDENTIFIER>256</IDENTIFIER></CAN-FRAME-TRIGGERING>\
+                 <CAN-FRAME-TRIGGERING><FRAME-REF DEST=\"CAN-FRAME\">/Car/Brake</FRAME-REF>\
+                 <IDENTIFIER>256</IDENTIFIER></CAN-FRAME-TRIGGERING>{triggering}"
+                ),
+                1,
+            )
+            .replacen(
+                "<CAN-FRAME>",
+                "<CAN-FRAME><SHORT-NAME>Brake</SHORT-NAME></CAN-FRAME><CAN-FRAME>",
+                1,
+            );
+        let (_, warnings) = convert("car.arxml", brake.as_bytes()).unwrap();
+        assert_eq!(
+            warnings,
+            ["car.arxml: Brake has the same id as Engine, it was skipped"]
+        );
+
+        let error = convert("car.arxml", b"<AUTOSAR><AR-PACKAGES></AUTOSAR>").unwrap_err();
+        assert!(error.starts_with("car.arxml could not be read: it is not valid XML, line 1"));
+    }
+}
@@ -1,8 +1,17 @@
 mod app;
 mod dbc;
+mod dbc_editor;
+mod decode;
+mod derived;
+mod heatmap;
+mod import;
 mod messages;
+mod overview;
 mod plots;
+mod raw;
+mod series;
 mod side_panel;
+mod trace;
 mod widgets;
 
 pub use app::{App, SharedApp};
@@ -2,69 +2,221 @@ use can_dbc::MessageId;
 use chrono::{DateTime, Utc};
 use regex_macro::regex;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
 
 #[derive(Serialize, Deserialize, Clone, Default)]
-pub struct Messages(pub HashMap<RawCanMessageId, Vec<Message>>);
+pub struct Messages {
+    pub frames: HashMap<RawCanMessageId, Vec<Message>>,
+    // Every bus seen on the frames, kept here so that the UI doesnt have to walk all of them
+    pub buses: BTreeSet<Bus>,
+    // Bumped whenever frames change other than by being appended at the end, so whatever is
+    // computed from them knows when only the new frames need to be looked at
+    #[serde(skip)]
+    pub revision: u64,
+}
 
 impl Messages {
     pub fn from_string(string: Cow<'_, str>) -> Messages {
-        let mut messages: HashMap<RawCanMessageId, Vec<Message>> = HashMap::new();
+        let mut messages = Messages::empty();
         for (msg_id, message) in string
             .lines()
             .filter_map(|message_str| Message::from_str(message_str))
         {
-            messages.entry(msg_id).or_default().push(message);
+            messages.buses.insert(message.bus);
+            messages.frames.entry(msg_id).or_default().push(message);
         }
 
-        Messages(messages)
+        messages
     }
 
     pub fn empty() -> Messages {
-        Messages(HashMap::new())
+        Messages {
+            frames: HashMap::new(),
+            buses: BTreeSet::new(),
+            revision: 0,
+        }
     }
 
+    // Logs of different buses can be loaded one after the other, so frames of an already known id
+    // are merged instead of replaced
     pub fn extend(&mut self, other: &Messages) {
-        self.0
-            .extend(other.0.iter().map(|(id, msgs)| (*id, msgs.clone())));
+        for (id, msgs) in &other.frames {
+            let messages = self.frames.entry(*id).or_default();
+            messages.extend(msgs.iter().cloned());
+            messages.sort_by_key(|msg| msg.timestamp);
+        }
+        self.buses.extend(other.buses.iter().copied());
+        self.revision += 1;
     }
 
     pub fn push(&mut self, id: RawCanMessageId, msg: Message) {
-        let messages = self.0.entry(id).or_default();
-        let idx = match messages.binary_search_by(|msg_to_comp_against| {
-            msg_to_comp_against.timestamp.cmp(&msg.timestamp)
-        }) {
-            Ok(idx) => idx,
-            Err(idx) => idx,
-        };
+        self.buses.insert(msg.bus);
+
+        let messages = self.frames.entry(id).or_default();
+        // After the frames with the same timestamp, so that frames in order are always appended
+        let idx = messages
+            .partition_point(|msg_to_comp_against| msg_to_comp_against.timestamp <= msg.timestamp);
+        if idx != messages.len() {
+            self.revision += 1;
+        }
 
         messages.insert(idx, msg);
     }
 
+    pub fn first_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.frames
+            .values()
+            .filter_map(|messages| messages.first())
+            .map(|msg| msg.timestamp)
+            .min()
+    }
+
+    pub fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.frames
+            .values()
+            .filter_map(|messages| messages.last())
+            .map(|msg| msg.timestamp)
+            .max()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.buses.clear();
+        self.revision += 1;
+    }
+
     pub fn len(&self) -> usize {
-        self.0.iter().map(|(_k, messages)| messages.len()).sum()
+        self.frames
+            .iter()
+            .map(|(_k, messages)| messages.len())
+            .sum()
+    }
+}
+
+// Linux interface names are at most 15 bytes long, so they are stored inline instead of
+// allocating a string for every frame
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bus {
+    len: u8,
+    name: [u8; Bus::MAX_LEN],
+}
+
+impl Bus {
+    const MAX_LEN: usize = 15;
+
+    pub fn new(name: &str) -> Option<Bus> {
+        if name.len() > Bus::MAX_LEN {
+            return None;
+        }
+
+        let mut bus = Bus {
+            len: name.len() as u8,
+            name: [0; Bus::MAX_LEN],
+        };
+        bus.name[..name.len()].copy_from_slice(name.as_bytes());
+        Some(bus)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Always built from a &str
+        std::str::from_utf8(&self.name[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+pub const CAN_SFF_MASK: u32 = 0x000007FF;
+pub const CAN_EFF_MASK: u32 = 0x1FFFFFFF;
+pub const CAN_MAX_DLEN: usize = 8;
+pub const CANFD_MAX_DLEN: usize = 64;
+
+// Flags nibble of the candump FD syntax (ID##<flags><data>)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FdFlags {
+    // Bit rate switch
+    pub brs: bool,
+    // Error state indicator
+    pub esi: bool,
+}
+
+impl FdFlags {
+    const BRS: u8 = 0x01;
+    const ESI: u8 = 0x02;
+
+    fn from_nibble(nibble: u8) -> Self {
+        Self {
+            brs: nibble & Self::BRS != 0,
+            esi: nibble & Self::ESI != 0,
+        }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
-    pub contents: [u8; 8],
+    // Up to 8 bytes for classic frames and up to 64 for FD ones
+    pub contents: Vec<u8>,
+    // DLC as sent on the bus. Same as the payload length except for FD frames longer than 8 bytes
+    // and classic frames logged with a 9..=15 DLC (candump -8)
+    pub dlc: u8,
+    // None for classic CAN frames
+    pub fd_flags: Option<FdFlags>,
+    pub bus: Bus,
     pub timestamp: DateTime<Utc>,
 }
 
 impl Message {
     pub fn from_str(str: &str) -> Option<(RawCanMessageId, Message)> {
-        if let Some(captures) =
-            regex!(r"\(([\d.]+)\)\s+\w+\s+([0-9A-Fa-f]+)#([0-9A-Fa-f]+)").captures(str)
+        if let Some(captures) = regex!(
+            r"\(([\d.]+)\)\s+(\w+)\s+([0-9A-Fa-f]+)#(?:#([0-9A-Fa-f]))?([0-9A-Fa-f]*)(?:_([0-9A-Fa-f]))?(?:\s|$)"
+        )
+        .captures(str)
         {
-            let Ok(id) = u32::from_str_radix(&captures[2], 16) else {
-                return None;
+            let bus = Bus::new(&captures[2])?;
+
+            let id = RawCanMessageId::from_hex(&captures[3])?;
+
+            let fd_flags = match captures.get(4) {
+                Some(flags) => {
+                    let Ok(nibble) = u8::from_str_radix(flags.as_str(), 16) else {
+                        return None;
+                    };
+                    Some(FdFlags::from_nibble(nibble))
+                }
+                None => None,
             };
 
-            use hex::FromHex;
-            let Ok(contents) = <[u8; 8]>::from_hex(&captures[3]) else {
+            let Ok(contents) = hex::decode(&captures[5]) else {
                 return None;
             };
+            let dlc = match (fd_flags, captures.get(6)) {
+                (Some(_), None) => can_fd_len_to_dlc(contents.len())?,
+                (None, None) if contents.len() <= CAN_MAX_DLEN => contents.len() as u8,
+                // Raw DLC values above 8 are only allowed on full classic frames
+                (None, Some(len8_dlc)) if contents.len() == CAN_MAX_DLEN => {
+                    let Ok(dlc) = u8::from_str_radix(len8_dlc.as_str(), 16) else {
+                        return None;
+                    };
+                    if dlc <= CAN_MAX_DLEN as u8 {
+                        return None;
+                    }
+                    dlc
+                }
+                _ => return None,
+            };
 
             let mut timestamp_str = captures[1].split(".");
             let Some(seconds_str) = timestamp_str.next() else {
@@ -88,9 +240,12 @@ impl Message {
             };
 
             Some((
-                RawCanMessageId(id),
+                id,
                 Message {
                     contents,
+                    dlc,
+                    fd_flags,
+                    bus,
                     timestamp,
                 },
             ))
@@ -100,22 +255,151 @@ impl Message {
     }
 }
 
-// The MessageId::raw() method adds the extended tag bit, which is inconvenient for this use case
-#[derive(Eq, Hash, PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
-#[repr(transparent)]
-pub struct RawCanMessageId(pub u32);
+// A standard 0x100 and an extended 0x00000100 are different messages, so the IDE bit is kept
+// apart from the id itself
+#[derive(Eq, Hash, PartialEq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RawCanMessageId {
+    pub id: u32,
+    pub extended: bool,
+}
+
+impl RawCanMessageId {
+    // Hex digits of an id as candump writes it. candump always writes extended ids with 8 digits,
+    // but be lenient with ids that can only be extended
+    pub fn from_hex(digits: &str) -> Option<Self> {
+        let id = u32::from_str_radix(digits, 16).ok()?;
+        if id > CAN_EFF_MASK {
+            return None;
+        }
+        Some(Self {
+            id,
+            extended: digits.len() == 8 || id > CAN_SFF_MASK,
+        })
+    }
+}
+
+impl fmt::Display for RawCanMessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.extended {
+            write!(f, "0x{:08X}", self.id)
+        } else {
+            write!(f, "0x{:03X}", self.id)
+        }
+    }
+}
 
 impl From<MessageId> for RawCanMessageId {
     fn from(value: MessageId) -> Self {
         match value {
-            MessageId::Standard(id) => Self(id as u32),
-            MessageId::Extended(id) => Self(id),
+            MessageId::Standard(id) => Self {
+                id: id as u32,
+                extended: false,
+            },
+            MessageId::Extended(id) => Self { id, extended: true },
         }
     }
 }
 
+fn can_fd_len_to_dlc(len: usize) -> Option<u8> {
+    match len {
+        0..=CAN_MAX_DLEN => Some(len as u8),
+        12 => Some(9),
+        16 => Some(10),
+        20 => Some(11),
+        24 => Some(12),
+        32 => Some(13),
+        48 => Some(14),
+        CANFD_MAX_DLEN => Some(15),
+        _ => None,
+    }
+}
+
 fn parse_nanos(s: &str) -> Option<u32> {
     // Scale by 10^(9 - len) to get nanoseconds
     let nanos = s.parse::<u32>().ok()? * (10u32.pow(9 - (s.len() as u32)));
     Some(nanos)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fd_frames() {
+        let (_, frame) = Message::from_str("(1.0) can0 123##1AABB").unwrap();
+        assert_eq!(frame.contents, [0xAA, 0xBB]);
+        assert_eq!(frame.dlc, 2);
+        assert_eq!(
+            frame.fd_flags,
+            Some(FdFlags {
+                brs: true,
+                esi: false
+            })
+        );
+
+        // FD payloads longer than 8 bytes come in a few lengths with their own DLC
+        let (_, frame) =
+            Message::from_str(&format!("(1.0) can0 123##0{}", "00".repeat(12))).unwrap();
+        assert_eq!(frame.dlc, 9);
+        assert!(Message::from_str(&format!("(1.0) can0 123##0{}", "00".repeat(9))).is_none());
+    }
+
+    #[test]
+    fn classic_frames_with_a_raw_dlc() {
+        let (_, frame) = Message::from_str("(1.0) can0 123#1122334455667788_C").unwrap();
+        assert_eq!(frame.contents.len(), 8);
+        assert_eq!(frame.dlc, 12);
+        assert_eq!(frame.fd_flags, None);
+
+        // Only full frames can have a DLC above 8
+        assert!(Message::from_str("(1.0) can0 123#11223344_C").is_none());
+        assert!(Message::from_str("(1.0) can0 123#1122334455667788_8").is_none());
+    }
+
+    #[test]
+    fn standard_and_extended_ids() {
+        let messages = Messages::from_string(
+            "(1.0) can0 100#01\n(2.0) can0 00000100#02\n(3.0) can0 800#03".into(),
+        );
+        let standard = RawCanMessageId {
+            id: 0x100,
+            extended: false,
+        };
+        let extended = RawCanMessageId {
+            id: 0x100,
+            extended: true,
+        };
+        assert_ne!(standard, extended);
+        assert_eq!(messages.frames[&standard][0].contents, [0x01]);
+        assert_eq!(messages.frames[&extended][0].contents, [0x02]);
+        // Too large for a standard id
+        assert!(messages.frames.contains_key(&RawCanMessageId {
+            id: 0x800,
+            extended: true
+        }));
+
+        assert!(Message::from_str("(1.0) can0 20000000#01").is_none());
+        assert!(Message::from_str("(1.0) can0 1FFFFFFF#01").is_some());
+    }
+
+    #[test]
+    fn buses() {
+        let messages = Messages::from_string(
+            "(1.0) can1 100#01\n(2.0) vcan0 100#02\n(3.0) interface_too_long 100#03".into(),
+        );
+        let can1 = Bus::new("can1").unwrap();
+        let vcan0 = Bus::new("vcan0").unwrap();
+        assert_eq!(messages.buses, BTreeSet::from([can1, vcan0]));
+        let id = RawCanMessageId {
+            id: 0x100,
+            extended: false,
+        };
+        let buses: Vec<Bus> = messages.frames[&id].iter().map(|frame| frame.bus).collect();
+        assert_eq!(buses, [can1, vcan0]);
+        assert_eq!(vcan0.as_str(), "vcan0");
+
+        // Linux interface names are at most 15 bytes, longer ones can not be stored
+        assert!(Bus::new("fifteen_bytes__").is_some());
+        assert!(Bus::new("sixteen_bytes___").is_none());
+    }
+}
@@ -0,0 +1,227 @@
+use chrono::{DateTime, Utc};
+use egui::{RichText, Ui};
+use std::collections::HashMap;
+
+use crate::{
+    dbc::Dbcs,
+    messages::{Bus, Messages, RawCanMessageId},
+    series::RunningStats,
+};
+
+// Every id seen in the log with how often it is sent, on each bus on its own
+#[derive(Default)]
+pub struct Overview {
+    pub open: bool,
+    only_unknown: bool,
+    ids: HashMap<(RawCanMessageId, Bus), IdStats>,
+    // Frames of every id already counted, from every bus
+    counted: HashMap<RawCanMessageId, usize>,
+    // What the stats were counted from
+    revision: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct IdStats {
+    // Frames of the id on the bus counted so far
+    count: usize,
+    // Position of the last of them in the frames of the id
+    last_frame: usize,
+    last: Option<DateTime<Utc>>,
+    // Seconds between consecutive frames
+    intervals: RunningStats,
+    min_interval: Option<f64>,
+    max_interval: Option<f64>,
+}
+
+impl IdStats {
+    fn add(&mut self, timestamp: DateTime<Utc>) {
+        if let Some(last) = self.last {
+            let delta = timestamp - last;
+            let interval = delta.num_seconds() as f64 + delta.subsec_nanos() as f64 / 1e9;
+            self.intervals.add(interval);
+            self.min_interval = Some(self.min_interval.map_or(interval, |min| min.min(interval)));
+            self.max_interval = Some(self.max_interval.map_or(interval, |max| max.max(interval)));
+        }
+        self.count += 1;
+        self.last = Some(timestamp);
+    }
+
+    // Mean seconds between frames
+    fn period(&self) -> Option<f64> {
+        (self.intervals.count > 0).then_some(self.intervals.mean)
+    }
+
+    // Standard deviation of the seconds between frames
+    fn jitter(&self) -> Option<f64> {
+        (self.intervals.count > 0).then(|| self.intervals.std_dev())
+    }
+}
+
+impl Overview {
+    pub fn window(&mut self, ctx: &egui::Context, messages: &Messages, dbcs: &Dbcs) {
+        let mut open = self.open;
+        egui::Window::new("Message overview")
+            .open(&mut open)
+            .default_size([800., 400.])
+            .show(ctx, |ui| self.ui(ui, messages, dbcs));
+        self.open = open;
+    }
+
+    fn ui(&mut self, ui: &mut Ui, messages: &Messages, dbcs: &Dbcs) {
+        self.update(messages);
+
+        let mut ids: Vec<&(RawCanMessageId, Bus)> = self.ids.keys().collect();
+        ids.sort();
+        let unknown_count = ids
+            .iter()
+            .filter(|(id, bus)| message_name(dbcs, *id, *bus).is_none())
+            .count();
+        ui.horizontal(|ui| {
+            ui.label(format!("{} ids, {unknown_count} unknown", ids.len()));
+            ui.checkbox(&mut self.only_unknown, "Only unknown");
+        });
+        ui.separator();
+
+        let period_label = |seconds: Option<f64>| {
+            seconds
+                .map(|seconds| format!("{:.3} ms", seconds * 1e3))
+                .unwrap_or_default()
+        };
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("message_overview")
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in [
+                        "ID",
+                        "Bus",
+                        "Message",
+                        "Count",
+                        "Period",
+                        "Min",
+                        "Max",
+                        "Jitter",
+                        "Last data",
+                    ] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+
+                    for key in ids {
+                        let (id, bus) = key;
+                        let name = message_name(dbcs, *id, *bus);
+                        if self.only_unknown && name.is_some() {
+                            continue;
+                        }
+                        let stats = &self.ids[key];
+
+                        match name {
+                            Some(name) => {
+                                ui.monospace(id.to_string());
+                                ui.label(bus.as_str());
+                                ui.label(name);
+                            }
+                            // Undocumented traffic stands out
+                            None => {
+                                let color = ui.visuals().warn_fg_color;
+                                ui.label(RichText::new(id.to_string()).monospace().color(color));
+                                ui.label(bus.as_str());
+                                ui.colored_label(color, "⚠ Unknown");
+                            }
+                        }
+                        ui.label(stats.count.to_string());
+                        ui.label(period_label(stats.period()));
+                        ui.label(period_label(stats.min_interval));
+                        ui.label(period_label(stats.max_interval));
+                        ui.label(period_label(stats.jitter()));
+                        let data = messages
+                            .frames
+                            .get(id)
+                            .and_then(|frames| frames.get(stats.last_frame))
+                            .map(|frame| {
+                                frame
+                                    .contents
+                                    .iter()
+                                    .map(|byte| format!("{byte:02X}"))
+                                    .collect::<Vec<_>>()
+                                    .join(" ")
+                            })
+                            .unwrap_or_default();
+                        ui.monospace(data);
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    // Counts the new frames of every id, or all of them again when the frames changed otherwise
+    fn update(&mut self, messages: &Messages) {
+        if self.revision != messages.revision {
+            self.ids.clear();
+            self.counted.clear();
+            self.revision = messages.revision;
+        }
+
+        for (id, frames) in &messages.frames {
+            let counted = self.counted.entry(*id).or_default();
+            for (idx, frame) in frames.iter().enumerate().skip(*counted) {
+                let stats = self.ids.entry((*id, frame.bus)).or_default();
+                stats.add(frame.timestamp);
+                stats.last_frame = idx;
+            }
+            *counted = frames.len();
+        }
+    }
+}
+
+// "dbc: message" of the dbc that decodes the id on the bus
+fn message_name(dbcs: &Dbcs, id: RawCanMessageId, bus: Bus) -> Option<String> {
+    let dbc = dbcs.message_owner(id, bus)?;
+    let message = dbc.messages_map.get(&id)?;
+    Some(format!("{}: {}", dbc.name, message.message_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Message;
+
+    #[test]
+    fn periods() {
+        let mut messages = Messages::from_string(
+            "(1.00) can0 100#01\n(1.05) can1 100#0A\n(1.10) can0 100#02\n(1.30) can0 100#03\n\
+            (1.00) can0 200#01"
+                .into(),
+        );
+        let mut overview = Overview::default();
+        overview.update(&messages);
+        let id = RawCanMessageId {
+            id: 0x100,
+            extended: false,
+        };
+        let can0 = Bus::new("can0").unwrap();
+        // The frame on can1 does not count for can0
+        let stats = overview.ids[&(id, can0)];
+        assert_eq!(stats.count, 3);
+        assert!((stats.period().unwrap() - 0.15).abs() < 1e-9);
+        assert!((stats.min_interval.unwrap() - 0.1).abs() < 1e-9);
+        assert!((stats.max_interval.unwrap() - 0.2).abs() < 1e-9);
+        assert!((stats.jitter().unwrap() - 0.05).abs() < 1e-9);
+
+        // A single frame has no period
+        let other = RawCanMessageId {
+            id: 0x200,
+            extended: false,
+        };
+        assert_eq!(overview.ids[&(other, can0)].period(), None);
+        assert_eq!(overview.ids[&(id, Bus::new("can1").unwrap())].count, 1);
+
+        // New frames are added to the counted ones
+        let (_, frame) = Message::from_str("(1.60) can0 100#04").unwrap();
+        messages.push(id, frame);
+        overview.update(&messages);
+        let stats = overview.ids[&(id, can0)];
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.last_frame, 4);
+        assert!((stats.max_interval.unwrap() - 0.3).abs() < 1e-9);
+    }
+}
@@ -1,41 +1,407 @@
-use egui::{Frame, Layout, Rect, Ui, UiBuilder};
-use egui_plot::{Legend, Line, PlotPoints};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use egui::{Align2, Color32, Frame, Layout, Rect, Stroke, Ui, UiBuilder, epaint::Hsva};
+use egui_plot::{Legend, Line, PlotPoint, PlotPoints, Points, Polygon, Text, VLine};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
 use crate::{
     App,
-    dbc::{Dbc, Signal},
+    dbc::{Dbcs, Signal},
+    dbc_editor::SignalPosition,
+    decode::SignalDecoder,
+    derived::{DerivedChange, DerivedSignal, DerivedSignals},
     messages::Messages,
+    series::{Channel, Series, SeriesCache, StatsCache, epoch_seconds},
     widgets,
 };
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub struct Plots(Vec<Plot>);
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Plots {
+    plots: Vec<Plot>,
+    time_origin: TimeOrigin,
+    // Keep the last follow_window seconds in view, stops when the user moves a plot
+    follow: bool,
+    follow_window: f64,
+    // Measurement cursors, shown on every plot
+    cursors: Option<[DateTime<Utc>; 2]>,
+    #[serde(skip)]
+    cursor_drag: CursorDrag,
+    derived: DerivedSignals,
+    // Time to center every plot on in the next frame
+    #[serde(skip)]
+    jump: Option<DateTime<Utc>>,
+    // Of the signals between the cursors
+    #[serde(skip)]
+    readout: StatsCache,
+}
+
+// Which cursor is under the pointer or being dragged, from the last frame
+#[derive(Debug, Default, Clone, Copy)]
+struct CursorDrag {
+    hovered: Option<usize>,
+    dragged: Option<usize>,
+}
+
+impl CursorDrag {
+    // The plots must not pan while a cursor is grabbed
+    fn grabbing(&self) -> bool {
+        self.hovered.is_some() || self.dragged.is_some()
+    }
+}
+
+impl Default for Plots {
+    fn default() -> Self {
+        Self {
+            plots: Vec::new(),
+            time_origin: TimeOrigin::default(),
+            follow: false,
+            follow_window: 10.,
+            cursors: None,
+            cursor_drag: CursorDrag::default(),
+            derived: DerivedSignals::default(),
+            jump: None,
+            readout: StatsCache::default(),
+        }
+    }
+}
+
+// What t = 0 is on the time axis, shared by every plot
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+enum TimeOrigin {
+    #[default]
+    FirstFrame,
+    // Moved with a right click on any plot
+    Marker(DateTime<Utc>),
+    // Seconds since the unix epoch, shown as local time of day
+    WallClock,
+}
+
+// Everything a plot needs from the rest of the app
+struct DrawContext<'a> {
+    dbcs: &'a Dbcs,
+    derived: &'a [DerivedSignal],
+    series: &'a SeriesCache,
+    origin: DateTime<Utc>,
+    // The origin in the seconds since the unix epoch of the series
+    origin_seconds: f64,
+    time_origin: TimeOrigin,
+    // Time range every plot is moved to while following the data
+    follow_range: Option<RangeInclusive<f64>>,
+    cursors: Option<[f64; 2]>,
+    cursor_drag: CursorDrag,
+    jump: Option<f64>,
+}
+
+#[derive(Default)]
+struct PlotActions {
+    should_close: bool,
+    // Time of a right click on the plot
+    clicked_time: Option<DateTime<Utc>>,
+    // Panned or zoomed by the user
+    moved_view: bool,
+    hovered_cursor: Option<usize>,
+    // Cursor being dragged and where to
+    moved_cursor: Option<(usize, DateTime<Utc>)>,
+    // Seconds since the unix epoch shown by the plot
+    visible_range: Option<(f64, f64)>,
+}
+
+impl PlotActions {
+    fn merge(&mut self, other: PlotActions) {
+        self.should_close |= other.should_close;
+        self.clicked_time = self.clicked_time.or(other.clicked_time);
+        self.moved_view |= other.moved_view;
+        self.hovered_cursor = self.hovered_cursor.or(other.hovered_cursor);
+        self.moved_cursor = self.moved_cursor.or(other.moved_cursor);
+        self.visible_range = self.visible_range.or(other.visible_range);
+    }
+}
 
 impl Plots {
     pub fn add_one(&mut self) {
-        self.0.push(Plot::new());
+        self.plots.push(Plot::new());
     }
 
-    pub fn draw(app: &mut App, ui: &mut Ui) {
-        let Some(dbc) = &app.dbc else {
-            ui.heading("No Dbc loaded");
+    pub fn time_origin_ui(&mut self, ui: &mut Ui, messages: &Messages) {
+        let selected_text = match self.time_origin {
+            TimeOrigin::FirstFrame => "First frame",
+            TimeOrigin::Marker(_) => "Marker",
+            TimeOrigin::WallClock => "Wall clock",
+        };
+        let marker = match self.time_origin {
+            TimeOrigin::Marker(marker) => marker,
+            _ => messages.first_timestamp().unwrap_or_default(),
+        };
+
+        egui::ComboBox::from_id_salt("time_origin")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.time_origin, TimeOrigin::FirstFrame, "First frame");
+                ui.selectable_value(&mut self.time_origin, TimeOrigin::Marker(marker), "Marker")
+                    .on_hover_text("Right click on a plot to move it");
+                ui.selectable_value(&mut self.time_origin, TimeOrigin::WallClock, "Wall clock");
+            });
+        ui.label("Time origin:");
+    }
+
+    pub fn cursors_ui(&mut self, ui: &mut Ui, messages: &Messages) {
+        let mut show_cursors = self.cursors.is_some();
+        if !ui
+            .toggle_value(&mut show_cursors, "Cursors")
+            .on_hover_text("Drag them to measure between two times")
+            .changed()
+        {
             return;
+        }
+
+        self.cursors = show_cursors.then(|| {
+            // Start at a third and two thirds of the log
+            let first = messages.first_timestamp().unwrap_or_default();
+            let third = (messages.last_timestamp().unwrap_or_default() - first) / 3;
+            [first + third, first + third * 2]
+        });
+    }
+
+    pub fn follow_ui(&mut self, ui: &mut Ui) {
+        ui.add(
+            egui::DragValue::new(&mut self.follow_window)
+                .range(0.1..=3600.)
+                .speed(0.1)
+                .suffix(" s"),
+        );
+        ui.toggle_value(&mut self.follow, "Follow")
+            .on_hover_text("Keep the latest frames in view, moving a plot pauses it");
+    }
+
+    fn origin(&self, messages: &Messages) -> DateTime<Utc> {
+        match self.time_origin {
+            TimeOrigin::FirstFrame => messages.first_timestamp().unwrap_or_default(),
+            TimeOrigin::Marker(marker) => marker,
+            TimeOrigin::WallClock => DateTime::UNIX_EPOCH,
+        }
+    }
+
+    pub fn has_cursors(&self) -> bool {
+        self.cursors.is_some()
+    }
+
+    // Time between the cursors, earliest first
+    pub fn cursor_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let [first, second] = self.cursors?;
+        Some((first.min(second), first.max(second)))
+    }
+
+    // Centers the plots on the time keeping their zoom
+    pub fn jump_to(&mut self, time: DateTime<Utc>) {
+        self.jump = Some(time);
+        self.follow = false;
+    }
+
+    // Plots follow the derived signals that are renamed and drop the removed ones
+    pub fn derived_signals_ui(&mut self, ui: &mut Ui, dbcs: &Dbcs) {
+        let Some(change) = self.derived.ui(ui, dbcs) else {
+            return;
+        };
+        for plot in &mut self.plots {
+            match &change {
+                DerivedChange::Renamed(old_name, new_name) => {
+                    for channel in plot.signals.iter_mut().chain(&mut plot.x_signal) {
+                        if **channel == Channel::Derived(old_name.clone()) {
+                            *channel = Arc::new(Channel::Derived(new_name.clone()));
+                        }
+                    }
+                }
+                DerivedChange::Removed(name) => {
+                    let removed = Channel::Derived(name.clone());
+                    plot.signals.retain(|channel| **channel != removed);
+                    plot.x_signal.take_if(|channel| **channel == removed);
+                }
+            }
+        }
+    }
+
+    // Points the plotted signals of an edited dbc to where they are now, removing the ones that
+    // were removed from it
+    pub fn dbc_edited(&mut self, dbc: &str, moved: &HashMap<SignalPosition, SignalPosition>) {
+        let follow = |channel: &Arc<Channel>| match &**channel {
+            Channel::Dbc(signal) if &*signal.dbc == dbc => {
+                let (message_id, signal_idx) =
+                    *moved.get(&(signal.message_id, signal.signal_idx))?;
+                Some(Arc::new(Channel::Dbc(Signal {
+                    dbc: signal.dbc.clone(),
+                    message_id,
+                    signal_idx,
+                })))
+            }
+            _ => Some(channel.clone()),
+        };
+        for plot in &mut self.plots {
+            plot.signals = plot.signals.iter().filter_map(follow).collect();
+            plot.x_signal = plot.x_signal.as_ref().and_then(follow);
+        }
+    }
+
+    // Plotted signals of a dbc that is loaded under another name now
+    pub fn dbc_renamed(&mut self, from: &str, to: &Arc<str>) {
+        let rename = |channel: &mut Arc<Channel>| {
+            if let Channel::Dbc(signal) = &**channel
+                && &*signal.dbc == from
+            {
+                *channel = Arc::new(Channel::Dbc(Signal {
+                    dbc: to.clone(),
+                    ..signal.clone()
+                }));
+            }
+        };
+        for plot in &mut self.plots {
+            plot.signals.iter_mut().for_each(rename);
+            if let Some(channel) = &mut plot.x_signal {
+                rename(channel);
+            }
+            // The same signal may have been plotted from both
+            let mut seen = HashSet::new();
+            plot.signals.retain(|channel| seen.insert(channel.clone()));
+        }
+    }
+
+    // Values of every plotted signal at the cursors and between them
+    pub fn cursor_readout_ui(app: &mut App, ui: &mut Ui) {
+        let Some(cursors) = app.plots.cursors else {
+            return;
+        };
+        let origin = app.plots.origin(&app.messages);
+        let time_label = |time: DateTime<Utc>| {
+            time_label(
+                epoch_seconds(time) - epoch_seconds(origin),
+                app.plots.time_origin,
+            )
         };
+        let [first, second] = cursors.map(epoch_seconds);
 
-        if app.plots.0.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label(format!("Cursor 1: {}", time_label(cursors[0])));
+            ui.separator();
+            ui.label(format!("Cursor 2: {}", time_label(cursors[1])));
+            ui.separator();
+            ui.label(format!("Δt: {:.6} s", second - first));
+        });
+
+        let mut signals: Vec<Channel> = Vec::new();
+        for signal in app.plots.plots.iter().flat_map(Plot::channels) {
+            if !signals.contains(signal) {
+                signals.push(signal.clone());
+            }
+        }
+        let between = Some((first.min(second), first.max(second)));
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("cursor_readout")
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in ["Signal", "Cursor 1", "Cursor 2", "Δ", "Min", "Max", "Mean"] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+
+                    for signal in &signals {
+                        let Some(resolved) = ResolvedSignal::new(
+                            &app.dbcs,
+                            &app.plots.derived.signals,
+                            &app.series,
+                            signal,
+                        ) else {
+                            continue;
+                        };
+                        let values = [first, second].map(|time| resolved.series.value_at(time));
+
+                        ui.label(resolved.label());
+                        for value in values {
+                            ui.label(
+                                value
+                                    .map(|value| resolved.value_label(value))
+                                    .unwrap_or_default(),
+                            );
+                        }
+                        match values {
+                            [Some(first), Some(second)] => {
+                                ui.label(format!("{:.3}", second - first))
+                            }
+                            _ => ui.label(""),
+                        };
+                        let Some(stats) = app.plots.readout.get(&app.series, signal, between)
+                        else {
+                            ui.end_row();
+                            continue;
+                        };
+                        ui.label(resolved.value_label(stats.min));
+                        ui.label(resolved.value_label(stats.max));
+                        ui.label(format!("{:.3}", stats.mean));
+                        ui.end_row();
+                    }
+                });
+        });
+        app.plots
+            .readout
+            .retain(&signals.iter().collect::<Vec<_>>());
+    }
+
+    pub fn draw(app: &mut App, ui: &mut Ui) {
+        if app.plots.plots.is_empty() {
             ui.heading("Add a plot to start");
             return;
         }
 
+        app.series.update(
+            app.plots.plots.iter().flat_map(Plot::channels),
+            &app.plots.derived.signals,
+            &app.dbcs,
+            &app.messages,
+            &app.hidden_buses,
+        );
+        app.series
+            .update_pairs(app.plots.plots.iter().flat_map(Plot::xy_pairs));
+
+        let time_origin = app.plots.time_origin;
+        let origin = app.plots.origin(&app.messages);
+        let draw_ctx = DrawContext {
+            dbcs: &app.dbcs,
+            derived: &app.plots.derived.signals,
+            series: &app.series,
+            origin,
+            origin_seconds: epoch_seconds(origin),
+            time_origin,
+            follow_range: app
+                .messages
+                .last_timestamp()
+                .filter(|_| app.plots.follow)
+                .map(|last| {
+                    let end = epoch_seconds(last) - epoch_seconds(origin);
+                    end - app.plots.follow_window..=end
+                }),
+            cursors: app
+                .plots
+                .cursors
+                .map(|cursors| cursors.map(|cursor| epoch_seconds(cursor) - epoch_seconds(origin))),
+            cursor_drag: app.plots.cursor_drag,
+            jump: app
+                .plots
+                .jump
+                .map(|jump| epoch_seconds(jump) - epoch_seconds(origin)),
+        };
+
         ui.vertical(|ui| {
             let total_height = ui.available_height();
-            let n = app.plots.0.len();
+            let n = app.plots.plots.len();
             let each_height = total_height / n as f32;
 
             let mut plots_to_close = Vec::new();
-            for (idx, plot) in app.plots.0.iter_mut().enumerate() {
+            let mut cursor_drag = CursorDrag::default();
+            for (idx, plot) in app.plots.plots.iter_mut().enumerate() {
                 let rect = ui
                     .allocate_space(egui::vec2(ui.available_width(), each_height))
                     .1;
@@ -46,14 +412,32 @@ impl Plots {
                     ..UiBuilder::new()
                 };
                 let plot_ui = &mut ui.new_child(ui_builder);
-                if plot.draw(plot_ui, idx, dbc, &app.messages) {
+                let actions = plot.draw(plot_ui, idx, &draw_ctx);
+                if actions.should_close {
                     plots_to_close.push(idx);
                 }
+                if let (TimeOrigin::Marker(_), Some(clicked_time)) =
+                    (time_origin, actions.clicked_time)
+                {
+                    app.plots.time_origin = TimeOrigin::Marker(clicked_time);
+                }
+                if actions.moved_view {
+                    app.plots.follow = false;
+                }
+                cursor_drag.hovered = cursor_drag.hovered.or(actions.hovered_cursor);
+                if let (Some(cursors), Some((cursor, time))) =
+                    (&mut app.plots.cursors, actions.moved_cursor)
+                {
+                    cursors[cursor] = time;
+                    cursor_drag.dragged = Some(cursor);
+                }
             }
+            app.plots.cursor_drag = cursor_drag;
+            app.plots.jump = None;
 
             plots_to_close.sort_by(|a, b| b.cmp(a));
             for plot_to_close in plots_to_close {
-                app.plots.0.remove(plot_to_close);
+                app.plots.plots.remove(plot_to_close);
             }
         });
     }
@@ -61,51 +445,200 @@ impl Plots {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Plot {
-    signals: Vec<Arc<Signal>>,
+    signals: Vec<Arc<Channel>>,
+    // Signals with different units go on separate stacked plots instead of sharing one y axis
+    group_by_unit: bool,
+    show_stats: bool,
+    stats_scope: StatsScope,
+    kind: PlotKind,
+    // X axis of an xy plot, the other signals go on the y axis
+    x_signal: Option<Arc<Channel>>,
+    // Color the points of an xy plot from blue to red by their time
+    color_by_time: bool,
+    #[serde(skip)]
+    stats: StatsCache,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+enum PlotKind {
+    #[default]
+    Time,
+    Xy,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
+enum StatsScope {
+    #[default]
+    Visible,
+    WholeLog,
 }
 
 impl Plot {
     fn new() -> Self {
         Self {
             signals: Vec::new(),
+            group_by_unit: true,
+            show_stats: false,
+            stats_scope: StatsScope::default(),
+            kind: PlotKind::default(),
+            x_signal: None,
+            color_by_time: false,
+            stats: StatsCache::default(),
         }
     }
 
-    fn draw(&mut self, ui: &mut Ui, number: usize, dbc: &Dbc, messages: &Messages) -> bool {
-        let mut should_close = false;
-        let (_, new_signal) = ui.dnd_drop_zone::<Signal, _>(Frame::new().inner_margin(5), |ui| {
+    fn channels(&self) -> impl Iterator<Item = &Channel> {
+        // The x signal is kept for going back to xy, but a time plot does not show it
+        let x_signal = self.x_signal.iter().filter(|_| self.kind == PlotKind::Xy);
+        x_signal.chain(&self.signals).map(Arc::as_ref)
+    }
+
+    // X and y channel of every series of an xy plot
+    fn xy_pairs(&self) -> impl Iterator<Item = (&Channel, &Channel)> {
+        let x_signal = self
+            .x_signal
+            .as_deref()
+            .filter(|_| self.kind == PlotKind::Xy);
+        x_signal.into_iter().flat_map(|x_signal| {
+            self.signals
+                .iter()
+                .map(move |y_signal| (x_signal, y_signal.as_ref()))
+        })
+    }
+
+    fn draw(&mut self, ui: &mut Ui, number: usize, draw_ctx: &DrawContext) -> PlotActions {
+        let mut actions = PlotActions::default();
+        let (_, new_signal) = ui.dnd_drop_zone::<Channel, _>(Frame::new().inner_margin(5), |ui| {
             ui.heading(format!("Plot {}:", number + 1));
             let mut close_rect = ui.max_rect();
             close_rect.max.y = close_rect.min.y + 2.;
-            should_close = widgets::close_button_ui(ui, close_rect).clicked();
+            actions.should_close = widgets::close_button_ui(ui, close_rect).clicked();
 
             let max_rect = ui.max_rect();
             ui.horizontal(|ui| {
-                self.draw_plot(ui, dbc, number, max_rect, messages);
+                actions.merge(self.draw_plot(ui, number, max_rect, draw_ctx));
                 ui.separator();
-                self.draw_list(ui, dbc);
+                self.draw_list(ui, draw_ctx);
             });
         });
 
         if let Some(new_signal) = new_signal {
-            self.signals.push(new_signal);
+            if self.kind == PlotKind::Xy && self.x_signal.is_none() {
+                self.x_signal = Some(new_signal);
+            } else {
+                self.signals.push(new_signal);
+            }
+        }
+
+        if self.show_stats {
+            self.draw_stats(ui.ctx(), number, actions.visible_range, draw_ctx);
         }
 
-        should_close
+        actions
     }
 
-    fn draw_list(&mut self, ui: &mut Ui, dbc: &Dbc) {
+    fn draw_stats(
+        &mut self,
+        ctx: &egui::Context,
+        number: usize,
+        visible_range: Option<(f64, f64)>,
+        draw_ctx: &DrawContext,
+    ) {
+        let channels: Vec<Channel> = self.channels().cloned().collect();
+        let mut open = self.show_stats;
+        egui::Window::new(format!("Plot {} statistics", number + 1))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.stats_scope, StatsScope::Visible, "Visible");
+                    ui.selectable_value(&mut self.stats_scope, StatsScope::WholeLog, "Whole log");
+                });
+                let range = match self.stats_scope {
+                    StatsScope::Visible => visible_range,
+                    StatsScope::WholeLog => None,
+                };
+                let time_label = |seconds: f64| {
+                    time_label(seconds - draw_ctx.origin_seconds, draw_ctx.time_origin)
+                };
+
+                egui::ScrollArea::both().show(ui, |ui| {
+                    egui::Grid::new(("plot_stats", number))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for header in [
+                                "Signal", "Count", "Min", "Max", "Mean", "Std dev", "First",
+                                "Last", "Rate", "Jitter",
+                            ] {
+                                ui.strong(header);
+                            }
+                            ui.end_row();
+
+                            for signal in &channels {
+                                let Some(resolved) = draw_ctx.resolve(signal) else {
+                                    continue;
+                                };
+                                ui.label(resolved.label());
+                                let Some(stats) = self.stats.get(draw_ctx.series, signal, range)
+                                else {
+                                    ui.label("0");
+                                    ui.end_row();
+                                    continue;
+                                };
+                                ui.label(stats.count.to_string());
+                                for value in [stats.min, stats.max, stats.mean, stats.std_dev] {
+                                    ui.label(format!("{value:.3}"));
+                                }
+                                ui.label(time_label(stats.first));
+                                ui.label(time_label(stats.last));
+                                ui.label(format!("{:.2} Hz", stats.rate));
+                                ui.label(format!("{:.3} ms", stats.jitter * 1e3));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        self.show_stats = open;
+        self.stats.retain(&channels.iter().collect::<Vec<_>>());
+    }
+
+    fn draw_list(&mut self, ui: &mut Ui, draw_ctx: &DrawContext) {
         let mut signals_to_erase = Vec::new();
         ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.kind, PlotKind::Time, "Time");
+                ui.selectable_value(&mut self.kind, PlotKind::Xy, "XY")
+                    .on_hover_text("Plot signals against the first one dropped in");
+                ui.separator();
+                match self.kind {
+                    PlotKind::Time => ui
+                        .checkbox(&mut self.group_by_unit, "Group by unit")
+                        .on_hover_text("Plot each unit on its own y axis"),
+                    PlotKind::Xy => ui.checkbox(&mut self.color_by_time, "Color by time"),
+                };
+                ui.toggle_value(&mut self.show_stats, "Statistics");
+            });
+            if self.kind == PlotKind::Xy {
+                match self.x_signal.as_ref().and_then(|x| draw_ctx.resolve(x)) {
+                    Some(resolved) => {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("X: {} > {}", resolved.source, resolved.label()));
+                            if widgets::close_button_ui(ui, ui.max_rect()).clicked() {
+                                self.x_signal = None;
+                            }
+                        });
+                    }
+                    None => {
+                        ui.weak("Drop a signal for the x axis");
+                    }
+                }
+            }
             for (signal_plot_storage_idx, signal) in self.signals.iter().enumerate() {
-                let Some(message) = dbc.messages_map.get(&signal.message_id) else {
+                let Some(resolved) = draw_ctx.resolve(signal) else {
                     continue;
                 };
 
-                let signal = &message.signals()[signal.signal_idx];
-
                 ui.horizontal(|ui| {
-                    ui.label(format!("{} > {}", message.message_name(), signal.name()));
+                    ui.label(format!("{} > {}", resolved.source, resolved.label()));
                     if widgets::close_button_ui(ui, ui.max_rect()).clicked() {
                         signals_to_erase.push(signal_plot_storage_idx);
                     }
@@ -122,171 +655,647 @@ impl Plot {
     fn draw_plot(
         &mut self,
         ui: &mut Ui,
-        dbc: &Dbc,
         plot_idx: usize,
         max_rect: Rect,
-        messages: &Messages,
-    ) {
-        // TODO: this is local to each plot. So if 2 plots are created, their start instant will not match
-        // This might not be the expected behaviour by anyone
-        let Some(first_signal_id) = self.signals.first().map(|signal| signal.message_id) else {
-            // Still have to draw an empty one
-            egui_plot::Plot::new(plot_idx)
-                .height(max_rect.height() * 0.9)
-                .width(max_rect.width() * 0.8)
-                .legend(Legend::default())
-                .show(ui, |_| {});
-            return;
+        draw_ctx: &DrawContext,
+    ) -> PlotActions {
+        if self.kind == PlotKind::Xy {
+            self.draw_xy(ui, plot_idx, max_rect, draw_ctx);
+            return PlotActions::default();
+        }
+
+        let mut lines = Vec::new();
+        let mut lanes = Vec::new();
+        for (signal_number, signal) in self.signals.iter().enumerate() {
+            let Some(resolved) = draw_ctx.resolve(signal) else {
+                continue;
+            };
+            match resolved.decoder {
+                Some(decoder) if decoder.is_enum() => lanes.push(StateLane {
+                    name: resolved.name,
+                    decoder,
+                    series: resolved.series,
+                }),
+                _ => lines.push((signal_number, resolved)),
+            }
+        }
+
+        let height = max_rect.height() * 0.9;
+        let width = max_rect.width() * 0.8;
+        // Enum signals get their own strip under the numeric ones
+        let bands_height = match (lines.is_empty(), lanes.is_empty()) {
+            (_, true) => 0.,
+            (true, false) => height,
+            (false, false) => (lanes.len() as f32 * STATE_LANE_HEIGHT + 30.).min(height / 2.),
         };
-        let Some(initial_timestamp) = messages
-            .0
-            .get(&first_signal_id)
-            .map(|messages| {
-                // TODO: Code will be incorrect starting on year 2262 since it will overflow
-                messages.iter().next().map(|first_msg| unsafe {
-                    first_msg.timestamp.timestamp_nanos_opt().unwrap_unchecked()
-                })
-            })
-            .flatten()
-        else {
-            // Still have to draw an empty one
-            egui_plot::Plot::new(plot_idx)
-                .height(max_rect.height() * 0.9)
-                .width(max_rect.width() * 0.8)
-                .legend(Legend::default())
-                .show(ui, |_| {});
-            return;
+
+        let groups = if bands_height < height {
+            unit_groups(lines, self.group_by_unit)
+        } else {
+            Vec::new()
         };
+        let group_count = groups.len();
 
-        // Cuidado con usar usizes para ids en otro lado que entonces hay colisiones
-        egui_plot::Plot::new(plot_idx)
+        ui.vertical(|ui| {
+            let mut actions = PlotActions::default();
+            for (group_idx, (unit, lines)) in groups.into_iter().enumerate() {
+                // Only the bottom plot shows the time axis
+                let is_bottom = lanes.is_empty() && group_idx + 1 == group_count;
+                actions.merge(Self::draw_lines(
+                    ui,
+                    (plot_idx, &unit),
+                    egui::vec2(width, (height - bands_height) / group_count as f32),
+                    &unit,
+                    is_bottom,
+                    lines,
+                    draw_ctx,
+                ));
+            }
+            if !lanes.is_empty() {
+                actions.merge(draw_state_bands(
+                    ui,
+                    plot_idx,
+                    egui::vec2(width, bands_height),
+                    &lanes,
+                    draw_ctx,
+                ));
+            }
+            actions
+        })
+        .inner
+    }
+
+    fn draw_xy(&self, ui: &mut Ui, plot_idx: usize, max_rect: Rect, draw_ctx: &DrawContext) {
+        let x_signal = self
+            .x_signal
+            .as_ref()
+            .and_then(|x_signal| Some((x_signal, draw_ctx.resolve(x_signal)?)));
+        let y_signals: Vec<(usize, &Channel, ResolvedSignal)> = self
+            .signals
+            .iter()
+            .enumerate()
+            .filter_map(|(signal_number, signal)| {
+                Some((signal_number, signal.as_ref(), draw_ctx.resolve(signal)?))
+            })
+            .collect();
+        let mut units: Vec<&str> = Vec::new();
+        for (_, _, resolved) in &y_signals {
+            if !resolved.unit.is_empty() && !units.contains(&resolved.unit) {
+                units.push(resolved.unit);
+            }
+        }
+
+        let plot = egui_plot::Plot::new(("xy_plot", plot_idx))
             .height(max_rect.height() * 0.9)
             .width(max_rect.width() * 0.8)
             .legend(Legend::default())
-            .show(ui, |plot_ui| {
-                // Esto no deberia existir, es una aberracion que de alguna forma funciona
-                self.signals
+            .x_axis_label(
+                x_signal
+                    .as_ref()
+                    .map(|(_, resolved)| resolved.label())
+                    .unwrap_or_default(),
+            )
+            .y_axis_label(units.join(", "));
+
+        plot.show(ui, |plot_ui| {
+            let Some((x_channel, _)) = x_signal else {
+                return;
+            };
+            for (signal_number, y_channel, resolved) in y_signals {
+                let Some(points) = draw_ctx.series.pair(x_channel, y_channel) else {
+                    continue;
+                };
+                // Every few points are enough to see the shape of a long log
+                let step = points.len().div_ceil(MAX_XY_POINTS).max(1);
+                let points: Vec<[f64; 3]> = points
                     .iter()
-                    .map(|signal| {
-                        (
-                            signal.message_id,
-                            &dbc.messages_map[&signal.message_id].signals()[signal.signal_idx],
-                        )
-                    })
-                    .filter_map(|(message_id, signal)| {
-                        messages.0.get(&message_id).map(move |messages| {
-                            (
-                                signal.name(),
-                                messages.iter().map(move |recv_message| {
-                                    let y = decode_signal(signal, &recv_message.contents);
-
-                                    [
-                                        // TODO: Same as before, change on year 2262
-                                        (unsafe {
-                                            recv_message
-                                                .timestamp
-                                                .timestamp_nanos_opt()
-                                                .unwrap_unchecked()
-                                        } - initial_timestamp)
-                                            as f64
-                                            / 10.0e9,
-                                        y,
-                                    ]
-                                }),
-                            )
-                        })
-                    })
-                    .for_each(|(signal_name, positions)| {
-                        plot_ui.line(Line::new(signal_name, PlotPoints::from_iter(positions)));
-                    });
-            });
+                    .step_by(step)
+                    .filter(|[_, x, y]| !x.is_nan() && !y.is_nan())
+                    .copied()
+                    .collect();
+                let (Some(first), Some(last)) = (points.first(), points.last()) else {
+                    continue;
+                };
+                let name = resolved.label();
+
+                if !self.color_by_time {
+                    let points = points.iter().map(|[_, x, y]| [*x, *y]).collect();
+                    plot_ui.points(
+                        Points::new(name, PlotPoints::new(points))
+                            .color(line_color(signal_number))
+                            .radius(XY_POINT_RADIUS),
+                    );
+                    continue;
+                }
+
+                let duration = last[0] - first[0];
+                for chunk in points.chunks(points.len().div_ceil(XY_TIME_COLORS)) {
+                    let fraction = if duration > 0. {
+                        (chunk[0][0] - first[0]) / duration
+                    } else {
+                        0.
+                    };
+                    let chunk = chunk.iter().map(|[_, x, y]| [*x, *y]).collect();
+                    plot_ui.points(
+                        Points::new(&name, PlotPoints::new(chunk))
+                            .color(time_color(fraction))
+                            .radius(XY_POINT_RADIUS),
+                    );
+                }
+            }
+        });
+    }
+
+    fn draw_lines(
+        ui: &mut Ui,
+        id: impl std::hash::Hash,
+        size: egui::Vec2,
+        unit: &str,
+        show_time_axis: bool,
+        lines: Vec<(usize, ResolvedSignal)>,
+        draw_ctx: &DrawContext,
+    ) -> PlotActions {
+        // Cuidado con usar usizes para ids en otro lado que entonces hay colisiones
+        let plot = time_plot(id, draw_ctx)
+            .height(size.y)
+            .width(size.x)
+            .legend(Legend::default())
+            .show_axes([show_time_axis, true])
+            .y_axis_label(unit);
+
+        let response = plot.show(ui, |plot_ui| {
+            let actions = time_axis_actions(plot_ui, draw_ctx);
+            let range = draw_ctx.visible_range(plot_ui);
+            for (signal_number, resolved) in lines {
+                let name = resolved.label();
+                let color = line_color(signal_number);
+
+                // Frames where the signal could not be decoded leave a gap in the line
+                let mut segment = Vec::new();
+                let buckets = resolved.series.buckets(range, size.x as usize);
+                for bucket in buckets {
+                    if bucket.is_gap() {
+                        if !segment.is_empty() {
+                            plot_ui.line(Line::new(&name, PlotPoints::new(segment)).color(color));
+                            segment = Vec::new();
+                        }
+                        continue;
+                    }
+
+                    segment.extend(
+                        bucket
+                            .points()
+                            .map(|[x, y]| [x - draw_ctx.origin_seconds, y]),
+                    );
+                }
+                plot_ui.line(Line::new(name, PlotPoints::new(segment)).color(color));
+            }
+
+            draw_time_lines(plot_ui, draw_ctx);
+
+            actions
+        });
+
+        response.inner
     }
 }
 
-// https://docs.rs/can_decode/latest/src/can_decode/lib.rs.html#270-299
-// Could be made faster but i wont (simd + remove bitwise loops)
-fn decode_signal(signal_def: &can_dbc::Signal, data: &[u8]) -> f64 {
-    // Get signal properties
-    let start_bit = *signal_def.start_bit() as usize;
-    let signal_size = *signal_def.signal_size() as usize;
-    let byte_order = signal_def.byte_order();
-    let value_type = signal_def.value_type();
-    let factor = signal_def.factor();
-    let offset = signal_def.offset();
-
-    // Extract raw value based on byte order and signal properties
-    let raw_value = extract_signal_value(data, start_bit, signal_size, *byte_order);
-
-    // Convert to signed if needed
-    let raw_value = if *value_type == can_dbc::ValueType::Signed {
-        // Convert to signed based on signal size
-        let max_unsigned = (1u64 << signal_size) - 1;
-        let sign_bit = 1u64 << (signal_size - 1);
-
-        if raw_value & sign_bit != 0 {
-            // Negative number - extend sign
-            (raw_value | (!max_unsigned)) as i64 as f64
-        } else {
-            raw_value as f64
+// Splits the lines by unit, or keeps them together with every unit in the axis label
+fn unit_groups(
+    lines: Vec<(usize, ResolvedSignal)>,
+    group_by_unit: bool,
+) -> Vec<(String, Vec<(usize, ResolvedSignal)>)> {
+    let mut groups: Vec<(String, Vec<_>)> = Vec::new();
+    for line in lines {
+        let unit = line.1.unit;
+        match groups.iter_mut().find(|(group_unit, _)| group_unit == unit) {
+            Some((_, group)) => group.push(line),
+            None => groups.push((unit.to_owned(), vec![line])),
         }
-    } else {
-        raw_value as f64
-    };
+    }
+
+    if group_by_unit && !groups.is_empty() {
+        return groups;
+    }
 
-    // Apply scaling
-    let scaled_value = raw_value * factor + offset;
+    let units: Vec<&str> = groups
+        .iter()
+        .map(|(unit, _)| unit.as_str())
+        .filter(|unit| !unit.is_empty())
+        .collect();
+    let label = units.join(", ");
+    let mut lines: Vec<_> = groups.into_iter().flat_map(|(_, group)| group).collect();
+    // Keep the order of the plot's signal list for the legend
+    lines.sort_by_key(|(signal_number, _)| *signal_number);
+    vec![(label, lines)]
+}
 
-    scaled_value
+// A plotted signal with what is needed to draw it
+struct ResolvedSignal<'a> {
+    // Message of a dbc signal, or the kind of signal
+    source: &'a str,
+    name: Cow<'a, str>,
+    unit: &'a str,
+    // Only dbc signals have value descriptions
+    decoder: Option<SignalDecoder<'a>>,
+    series: &'a Series,
 }
 
-fn extract_signal_value(
-    data: &[u8],
-    start_bit: usize,
-    size: usize,
-    byte_order: can_dbc::ByteOrder,
-) -> u64 {
-    let mut result = 0u64;
-
-    match byte_order {
-        can_dbc::ByteOrder::LittleEndian => {
-            let start_byte = start_bit / 8;
-            let start_bit_in_byte = start_bit % 8;
-
-            let mut remaining_bits = size;
-            let mut current_byte = start_byte;
-            let mut bit_offset = start_bit_in_byte;
-
-            while remaining_bits > 0 && current_byte < data.len() {
-                let bits_in_this_byte = std::cmp::min(remaining_bits, 8 - bit_offset);
-                let mask = ((1u64 << bits_in_this_byte) - 1) << bit_offset;
-                let byte_value = ((data[current_byte] as u64) & mask) >> bit_offset;
-
-                result |= byte_value << (size - remaining_bits);
-
-                remaining_bits -= bits_in_this_byte;
-                current_byte += 1;
-                bit_offset = 0;
+impl<'a> ResolvedSignal<'a> {
+    fn new(
+        dbcs: &'a Dbcs,
+        derived: &'a [DerivedSignal],
+        series: &'a SeriesCache,
+        channel: &Channel,
+    ) -> Option<Self> {
+        let series = series.get(channel)?;
+        match channel {
+            Channel::Dbc(signal) => {
+                let dbc = dbcs.get(&signal.dbc)?;
+                let message = dbc.messages_map.get(&signal.message_id)?;
+                let definition = message.signals().get(signal.signal_idx)?;
+
+                Some(ResolvedSignal {
+                    source: message.message_name(),
+                    name: Cow::Borrowed(definition.name()),
+                    unit: definition.unit(),
+                    decoder: Some(SignalDecoder::new(&dbc.inner, message, definition)),
+                    series,
+                })
+            }
+            Channel::Raw(signal) => Some(ResolvedSignal {
+                source: "Raw",
+                name: Cow::Owned(signal.name()),
+                unit: "",
+                decoder: None,
+                series,
+            }),
+            Channel::Derived(name) => {
+                let definition = derived.iter().find(|derived| derived.name == *name)?;
+
+                Some(ResolvedSignal {
+                    source: "Derived",
+                    name: Cow::Borrowed(&definition.name),
+                    unit: &definition.unit,
+                    decoder: None,
+                    series,
+                })
             }
         }
-        can_dbc::ByteOrder::BigEndian => {
-            // Idk if this is right
-            let mut bit_pos = start_bit;
+    }
+
+    fn label(&self) -> String {
+        if self.unit.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{} [{}]", self.name, self.unit)
+        }
+    }
+
+    fn value_label(&self, value: f64) -> String {
+        match self
+            .decoder
+            .as_ref()
+            .and_then(|decoder| decoder.value_name(value))
+        {
+            Some(name) => format!("{name} ({value})"),
+            None => format!("{value:.3}"),
+        }
+    }
+}
+
+impl<'a> DrawContext<'a> {
+    fn resolve(&self, channel: &Channel) -> Option<ResolvedSignal<'a>> {
+        ResolvedSignal::new(self.dbcs, self.derived, self.series, channel)
+    }
+
+    fn time_at(&self, x: f64) -> DateTime<Utc> {
+        self.origin + TimeDelta::nanoseconds((x * 1e9) as i64)
+    }
+
+    // The shown time range in seconds since the unix epoch, None while the plot is fitting all the
+    // data into view so it gets the whole series
+    fn visible_range(&self, plot_ui: &egui_plot::PlotUi) -> Option<(f64, f64)> {
+        if plot_ui.auto_bounds().x {
+            return None;
+        }
+
+        let bounds = plot_ui.plot_bounds();
+        Some((
+            bounds.min()[0] + self.origin_seconds,
+            bounds.max()[0] + self.origin_seconds,
+        ))
+    }
+}
+
+// Height in points of each enum signal in the state bands
+const STATE_LANE_HEIGHT: f32 = 36.;
 
-            for _ in 0..size {
-                let byte_idx = bit_pos / 8;
-                let bit_idx = 7 - (bit_pos % 8);
+// An enum signal drawn as a logic analyzer style band of named states
+struct StateLane<'a> {
+    name: Cow<'a, str>,
+    decoder: SignalDecoder<'a>,
+    series: &'a Series,
+}
+
+// Time range in which the signal kept the same value, None if it changed too fast to show
+struct StateRun {
+    start: f64,
+    end: f64,
+    value: Option<f64>,
+}
 
-                if byte_idx >= data.len() {
-                    break;
+impl StateLane<'_> {
+    fn runs(&self, range: Option<(f64, f64)>, max_buckets: usize, origin: f64) -> Vec<StateRun> {
+        let mut runs: Vec<StateRun> = Vec::new();
+        // Whether the last run is still going, frames without the signal end it
+        let mut open = false;
+        for bucket in self.series.buckets(range, max_buckets) {
+            let (start, end) = (bucket.start - origin, bucket.end - origin);
+            let value = (bucket.min[1] == bucket.max[1]).then_some(bucket.min[1]);
+            if open && let Some(last) = runs.last_mut() {
+                last.end = start;
+                if !bucket.is_gap() && value == last.value {
+                    last.end = end;
+                    continue;
                 }
+            }
+
+            open = !bucket.is_gap();
+            if open {
+                runs.push(StateRun { start, end, value });
+            }
+        }
+        runs
+    }
+
+    fn state_label(&self, value: Option<f64>) -> String {
+        match (
+            value,
+            value.and_then(|value| self.decoder.value_name(value)),
+        ) {
+            (Some(value), Some(name)) => format!("{name} ({value})"),
+            (Some(value), None) => value.to_string(),
+            (None, _) => String::from("Several values"),
+        }
+    }
+}
+
+fn run_at(runs: &[StateRun], x: f64) -> Option<&StateRun> {
+    let idx = runs.partition_point(|run| run.end < x);
+    runs.get(idx).filter(|run| run.start <= x)
+}
+
+fn draw_state_bands(
+    ui: &mut Ui,
+    plot_idx: usize,
+    size: egui::Vec2,
+    lanes: &[StateLane],
+    draw_ctx: &DrawContext,
+) -> PlotActions {
+    // Lane 0 is at the top, each one is a unit high in plot coordinates
+    let lane_bottom = |lane_idx: usize| (lanes.len() - 1 - lane_idx) as f64;
+
+    let plot = time_plot(("state_bands", plot_idx), draw_ctx)
+        .height(size.y)
+        .width(size.x)
+        .show_axes([true, false])
+        .show_grid([true, false])
+        .show_x(false)
+        .show_y(false)
+        .allow_zoom([true, false])
+        .allow_drag([!draw_ctx.cursor_drag.grabbing(), false])
+        .allow_scroll([true, false])
+        .include_y(0.)
+        .include_y(lanes.len() as f64);
 
-                let bit_val = (data[byte_idx] >> bit_idx) & 1;
-                result = (result << 1) | (bit_val as u64);
+    let response = plot.show(ui, |plot_ui| {
+        let actions = time_axis_actions(plot_ui, draw_ctx);
+        let bounds = plot_ui.plot_bounds();
+        let (min_x, max_x) = (bounds.min()[0], bounds.max()[0]);
+        let points_per_second = plot_ui.transform().dpos_dvalue_x();
+        let range = draw_ctx.visible_range(plot_ui);
 
-                bit_pos += 1;
+        let lane_runs: Vec<Vec<StateRun>> = lanes
+            .iter()
+            .map(|lane| lane.runs(range, size.x as usize, draw_ctx.origin_seconds))
+            .collect();
+        for (lane_idx, (lane, runs)) in lanes.iter().zip(&lane_runs).enumerate() {
+            let bottom = lane_bottom(lane_idx) + 0.1;
+            let top = bottom + 0.6;
+
+            for run in runs
+                .iter()
+                .filter(|run| run.end >= min_x && run.start <= max_x)
+            {
+                let color = state_color(lane, run.value);
+                plot_ui.polygon(
+                    Polygon::new(
+                        &*lane.name,
+                        PlotPoints::new(vec![
+                            [run.start, bottom],
+                            [run.end, bottom],
+                            [run.end, top],
+                            [run.start, top],
+                        ]),
+                    )
+                    .fill_color(color.gamma_multiply(0.4))
+                    .stroke(Stroke::new(1., color))
+                    .allow_hover(false),
+                );
+
+                // Only label the states that have room for it, centered on their visible part
+                let Some(value) = run.value else {
+                    continue;
+                };
+                let label = match lane.decoder.value_name(value) {
+                    Some(name) => name.to_owned(),
+                    None => value.to_string(),
+                };
+                let (start, end) = (run.start.max(min_x), run.end.min(max_x));
+                if (end - start) * points_per_second > label.len() as f64 * 8. {
+                    plot_ui.text(
+                        Text::new(
+                            &*lane.name,
+                            PlotPoint::new((start + end) / 2., (bottom + top) / 2.),
+                            label,
+                        )
+                        .allow_hover(false),
+                    );
+                }
             }
+
+            plot_ui.text(
+                Text::new(&*lane.name, PlotPoint::new(min_x, top), &*lane.name)
+                    .anchor(Align2::LEFT_BOTTOM)
+                    .allow_hover(false),
+            );
         }
+
+        draw_time_lines(plot_ui, draw_ctx);
+
+        let hovered = plot_ui.pointer_coordinate().and_then(|pointer| {
+            let lane_idx = lanes.len().checked_sub(pointer.y.floor() as usize + 1)?;
+            let lane = lanes.get(lane_idx).filter(|_| pointer.y >= 0.)?;
+            let run = run_at(&lane_runs[lane_idx], pointer.x)?;
+            Some(format!("{}: {}", &*lane.name, lane.state_label(run.value)))
+        });
+        (hovered, actions)
+    });
+
+    let (hovered, actions) = response.inner;
+    if let Some(hovered) = hovered {
+        response.response.on_hover_text_at_pointer(hovered);
+    }
+    actions
+}
+
+// Unnamed values are drawn in gray so they stand out from the states in the value table
+fn state_color(lane: &StateLane, value: Option<f64>) -> Color32 {
+    match value {
+        Some(value) if lane.decoder.value_name(value).is_some() => line_color(value.abs() as usize),
+        Some(_) => Color32::GRAY,
+        None => Color32::DARK_GRAY,
     }
+}
+
+// A plot with the time axis shared by every plot
+fn time_plot<'a>(id: impl std::hash::Hash, draw_ctx: &DrawContext) -> egui_plot::Plot<'a> {
+    let plot = egui_plot::Plot::new(id)
+        // Every plot shows the same time range
+        .link_axis("time_axis", [true, false])
+        .link_cursor("time_axis", [true, false])
+        .allow_drag(!draw_ctx.cursor_drag.grabbing())
+        .x_axis_label("Time [s]");
+    if draw_ctx.time_origin != TimeOrigin::WallClock {
+        return plot;
+    }
+
+    plot.x_axis_label("Time")
+        .x_axis_formatter(|mark, _range| wall_clock_label(mark.value))
+        .label_formatter(|name, point| {
+            format!("{name}\n{}\n{:.3}", wall_clock_label(point.x), point.y)
+        })
+}
+
+fn draw_time_lines(plot_ui: &mut egui_plot::PlotUi, draw_ctx: &DrawContext) {
+    if let TimeOrigin::Marker(_) = draw_ctx.time_origin {
+        plot_ui.vline(VLine::new("Marker", 0.));
+    }
+    for (cursor_idx, cursor) in draw_ctx.cursors.iter().flatten().enumerate() {
+        plot_ui.vline(
+            VLine::new(format!("Cursor {}", cursor_idx + 1), *cursor)
+                .color(CURSOR_COLOR)
+                .allow_hover(false),
+        );
+    }
+}
+
+// Moves the plot to the followed range unless the user is moving it, and gets right clicks and
+// cursor drags
+fn time_axis_actions(plot_ui: &mut egui_plot::PlotUi, draw_ctx: &DrawContext) -> PlotActions {
+    let response = plot_ui.response();
+
+    // Grabbed a few points around the line
+    let hovered_cursor = draw_ctx
+        .cursors
+        .zip(response.hover_pos())
+        .and_then(|(cursors, pos)| {
+            cursors.iter().position(|cursor| {
+                (plot_ui.transform().position_from_point_x(*cursor) - pos.x).abs() < 5.
+            })
+        });
+    let dragged_cursor = if response.dragged_by(egui::PointerButton::Primary) {
+        draw_ctx.cursor_drag.dragged.or(hovered_cursor)
+    } else {
+        None
+    };
+    if hovered_cursor.or(dragged_cursor).is_some() {
+        plot_ui
+            .ctx()
+            .set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+    }
+    let moved_cursor = dragged_cursor
+        .zip(plot_ui.pointer_coordinate())
+        .map(|(cursor, point)| (cursor, draw_ctx.time_at(point.x)));
+
+    let bounds = plot_ui.plot_bounds();
+    let visible_range = Some((
+        bounds.min()[0] + draw_ctx.origin_seconds,
+        bounds.max()[0] + draw_ctx.origin_seconds,
+    ));
+
+    let response = plot_ui.response();
+    let moved_view = (response.dragged() && dragged_cursor.is_none())
+        || response.double_clicked()
+        || (response.hovered()
+            && plot_ui.ctx().input(|input| {
+                input.smooth_scroll_delta != egui::Vec2::ZERO || input.zoom_delta() != 1.
+            }));
+    let clicked_time = response
+        .secondary_clicked()
+        .then(|| plot_ui.pointer_coordinate())
+        .flatten()
+        .map(|point| draw_ctx.time_at(point.x));
+
+    if let Some(follow_range) = &draw_ctx.follow_range
+        && !moved_view
+    {
+        plot_ui.set_plot_bounds_x(follow_range.clone());
+    }
+    if let Some(jump) = draw_ctx.jump {
+        let half_width = (bounds.max()[0] - bounds.min()[0]) / 2.;
+        plot_ui.set_plot_bounds_x(jump - half_width..=jump + half_width);
+    }
+
+    PlotActions {
+        clicked_time,
+        moved_view,
+        hovered_cursor,
+        moved_cursor,
+        visible_range,
+        ..Default::default()
+    }
+}
+
+// Seconds from the origin as shown on the time axis
+fn time_label(seconds: f64, time_origin: TimeOrigin) -> String {
+    match time_origin {
+        TimeOrigin::WallClock => wall_clock_label(seconds),
+        _ => format!("{seconds:.6} s"),
+    }
+}
+
+fn wall_clock_label(seconds: f64) -> String {
+    let whole_seconds = seconds.floor();
+    let Some(timestamp) = DateTime::from_timestamp(
+        whole_seconds as i64,
+        ((seconds - whole_seconds) * 1e9) as u32,
+    ) else {
+        return String::new();
+    };
+
+    DateTime::<Local>::from(timestamp)
+        .format("%H:%M:%S%.3f")
+        .to_string()
+}
+
+const CURSOR_COLOR: Color32 = Color32::from_rgb(255, 160, 0);
+
+// Points drawn for each signal of an xy plot at most
+const MAX_XY_POINTS: usize = 20_000;
+// Colors from the start to the end of the log when an xy plot is colored by time
+const XY_TIME_COLORS: usize = 16;
+const XY_POINT_RADIUS: f32 = 1.5;
+
+// Blue at the start of the log to red at the end
+fn time_color(fraction: f64) -> Color32 {
+    Hsva::new(0.66 * (1. - fraction as f32), 0.85, 0.6, 1.0).into()
+}
 
-    result
+// egui_plot's automatic palette is private, but all the segments of a signal need the same color
+// so they are shown as a single legend entry
+fn line_color(idx: usize) -> Color32 {
+    let golden_ratio = (5.0_f32.sqrt() - 1.0) / 2.0;
+    Hsva::new(idx as f32 * golden_ratio, 0.85, 0.5, 1.0).into()
 }
@@ -0,0 +1,170 @@
+use egui::{Id, Ui};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    decode,
+    messages::{Messages, RawCanMessageId},
+    series::Channel,
+};
+
+// Bits of a frame plotted without a dbc, for ids that no dbc describes
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct RawSignal {
+    pub id: RawCanMessageId,
+    // Like in a dbc, the least significant bit for little endian and the most significant one for
+    // big endian
+    pub start_bit: u16,
+    pub size: u8,
+    pub big_endian: bool,
+    pub signed: bool,
+}
+
+impl Default for RawSignal {
+    fn default() -> Self {
+        Self {
+            id: RawCanMessageId {
+                id: 0,
+                extended: false,
+            },
+            start_bit: 0,
+            size: 8,
+            big_endian: false,
+            signed: false,
+        }
+    }
+}
+
+impl RawSignal {
+    pub fn name(&self) -> String {
+        let sign = if self.signed { "i" } else { "u" };
+        let order = if self.big_endian { "BE" } else { "LE" };
+        if self.size == 8 && self.start_bit % 8 == if self.big_endian { 7 } else { 0 } {
+            return format!("{} byte {} {sign}8", self.id, self.start_bit / 8);
+        }
+        format!(
+            "{} bit {} {sign}{} {order}",
+            self.id, self.start_bit, self.size
+        )
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        decode::decode_raw(
+            data,
+            self.start_bit as usize,
+            self.size as usize,
+            if self.big_endian {
+                can_dbc::ByteOrder::BigEndian
+            } else {
+                can_dbc::ByteOrder::LittleEndian
+            },
+            self.signed,
+        )
+    }
+
+    fn byte(id: RawCanMessageId, byte: u16) -> Self {
+        Self {
+            id,
+            start_bit: byte * 8,
+            ..Default::default()
+        }
+    }
+
+    // Makes the signal a whole number of bytes starting at the byte it is in
+    fn align(&mut self, size: u8) {
+        let byte = self.start_bit / 8;
+        self.size = size;
+        self.start_bit = if self.big_endian {
+            byte * 8 + 7
+        } else {
+            byte * 8
+        };
+    }
+
+    // Builder for a signal of any id in the log and the bytes of every id, to drag into the plots
+    pub fn builder_ui(&mut self, ui: &mut Ui, messages: &Messages) {
+        let mut ids: Vec<&RawCanMessageId> = messages.frames.keys().collect();
+        ids.sort();
+        if ids.is_empty() {
+            ui.label("No frames loaded");
+            return;
+        }
+        if !ids.contains(&&self.id) {
+            self.id = *ids[0];
+        }
+
+        egui::Grid::new("raw_signal_builder").show(ui, |ui| {
+            ui.label("ID");
+            egui::ComboBox::from_id_salt("raw_signal_id")
+                .selected_text(self.id.to_string())
+                .show_ui(ui, |ui| {
+                    for id in &ids {
+                        ui.selectable_value(&mut self.id, **id, id.to_string());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Start bit");
+            ui.add(egui::DragValue::new(&mut self.start_bit).range(0..=511));
+            ui.end_row();
+
+            ui.label("Size");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.size).range(1..=64));
+                for size in [8, 16, 32] {
+                    if ui.small_button(format!("{size}")).clicked() {
+                        self.align(size);
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("Byte order");
+            ui.horizontal(|ui| {
+                let mut big_endian = self.big_endian;
+                ui.selectable_value(&mut big_endian, false, "Little endian");
+                ui.selectable_value(&mut big_endian, true, "Big endian");
+                if big_endian != self.big_endian {
+                    self.big_endian = big_endian;
+                    self.align(self.size.next_multiple_of(8).min(64));
+                }
+            });
+            ui.end_row();
+
+            ui.label("Signed");
+            ui.checkbox(&mut self.signed, "");
+            ui.end_row();
+        });
+
+        ui.dnd_drag_source(
+            Id::new("raw_signal_builder_source"),
+            Channel::Raw(self.clone()),
+            |ui| {
+                ui.strong(self.name());
+            },
+        )
+        .response
+        .on_hover_text("Drag into a plot");
+
+        egui::collapsing_header::CollapsingHeader::new("Bytes").show(ui, |ui| {
+            for id in ids {
+                egui::collapsing_header::CollapsingHeader::new(id.to_string())
+                    .id_salt(("raw_bytes", id))
+                    .show(ui, |ui| {
+                        let len = messages.frames[id]
+                            .last()
+                            .map(|frame| frame.contents.len())
+                            .unwrap_or_default();
+                        for byte in 0..len as u16 {
+                            ui.dnd_drag_source(
+                                Id::new(("raw_byte", id, byte)),
+                                Channel::Raw(RawSignal::byte(*id, byte)),
+                                |ui| {
+                                    ui.label(format!("Byte {byte}"));
+                                },
+                            );
+                        }
+                    });
+            }
+        });
+    }
+}
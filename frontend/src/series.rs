@@ -0,0 +1,930 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{
+    dbc::{Dbcs, Signal},
+    decode::SignalDecoder,
+    derived::{DerivedSignal, Evaluator, Expr},
+    messages::{Bus, Messages, RawCanMessageId},
+    raw::RawSignal,
+};
+
+// Each level of the pyramid merges this many buckets of the level below
+const LOD_FACTOR: usize = 4;
+
+// Something that can be dragged into a plot
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Dbc(Signal),
+    Raw(RawSignal),
+    // Name of a derived signal
+    Derived(Arc<str>),
+}
+
+// Decoded values of the plotted signals. New frames are decoded as they arrive, and the whole
+// series only when the frames, the buses or the dbc of its signal change. Derived signals follow
+// the new points of their inputs the same way
+#[derive(Default)]
+pub struct SeriesCache {
+    entries: HashMap<Channel, CachedSeries>,
+    // Every change to a series gets a new one, so derived signals know when to recompute
+    last_version: u64,
+    // Series resampled together for the xy plots, by x and y channel
+    pairs: HashMap<(Channel, Channel), CachedPair>,
+}
+
+#[derive(Default)]
+struct CachedPair {
+    // Of the x and y series
+    origins: [u64; 2],
+    versions: [u64; 2],
+    // Points of the x and y series already resampled
+    read: [usize; 2],
+    resampler: Resampler,
+    points: Vec<[f64; 3]>,
+}
+
+#[derive(Default)]
+struct CachedSeries {
+    // What the series was built from
+    buses: Vec<Bus>,
+    revision: u64,
+    // Frames of the message already decoded, from every bus
+    frame_count: usize,
+    derived: Option<DerivedState>,
+    // Version the series was started at, a new one means it was built again rather than appended
+    // to
+    origin: u64,
+    version: u64,
+    series: Series,
+}
+
+// What a derived signal was computed from, and the evaluator that carries on with the new points
+// of its signals
+struct DerivedState {
+    expression: String,
+    signals: Vec<Signal>,
+    // Of the series of the signals
+    origins: Vec<u64>,
+    versions: Vec<u64>,
+    // None for an invalid expression
+    evaluator: Option<Evaluator>,
+}
+
+impl SeriesCache {
+    // Builds the series of the given channels that are missing or outdated and drops the rest
+    pub fn update<'a>(
+        &mut self,
+        channels: impl Iterator<Item = &'a Channel>,
+        derived: &[DerivedSignal],
+        dbcs: &Dbcs,
+        messages: &Messages,
+        hidden_buses: &HashSet<Bus>,
+    ) {
+        let mut plotted = HashSet::new();
+        for channel in channels {
+            match channel {
+                Channel::Dbc(signal) => {
+                    self.update_signal(signal, dbcs, messages, hidden_buses);
+                }
+                Channel::Raw(signal) => {
+                    self.update_raw(signal, messages, hidden_buses);
+                }
+                Channel::Derived(name) => {
+                    let Some(definition) = derived.iter().find(|derived| derived.name == *name)
+                    else {
+                        continue;
+                    };
+                    for signal in
+                        self.update_derived(channel, definition, dbcs, messages, hidden_buses)
+                    {
+                        plotted.insert(Channel::Dbc(signal));
+                    }
+                }
+            }
+            plotted.insert(channel.clone());
+        }
+
+        self.entries.retain(|channel, _| plotted.contains(channel));
+    }
+
+    // Returns the version of the series, None if the signal is not in the loaded dbcs
+    fn update_signal(
+        &mut self,
+        signal: &Signal,
+        dbcs: &Dbcs,
+        messages: &Messages,
+        hidden_buses: &HashSet<Bus>,
+    ) -> Option<u64> {
+        let dbc = dbcs.get(&signal.dbc)?;
+        let message = dbc.messages_map.get(&signal.message_id)?;
+        let definition = message.signals().get(signal.signal_idx)?;
+        // Leave out the buses on which another dbc takes priority for this message
+        let buses: Vec<Bus> = messages
+            .buses
+            .iter()
+            .copied()
+            .filter(|bus| {
+                !hidden_buses.contains(bus)
+                    && dbcs
+                        .message_owner(signal.message_id, *bus)
+                        .is_some_and(|owner| owner.name == dbc.name)
+            })
+            .collect();
+        let decoder = SignalDecoder::new(&dbc.inner, message, definition);
+
+        Some(self.update_frames(
+            Channel::Dbc(signal.clone()),
+            signal.message_id,
+            buses,
+            messages,
+            // Frames of other multiplexor values leave a gap in the signal
+            |data| decoder.decode(data),
+        ))
+    }
+
+    fn update_raw(
+        &mut self,
+        signal: &RawSignal,
+        messages: &Messages,
+        hidden_buses: &HashSet<Bus>,
+    ) -> u64 {
+        let buses = messages
+            .buses
+            .iter()
+            .copied()
+            .filter(|bus| !hidden_buses.contains(bus))
+            .collect();
+
+        self.update_frames(
+            Channel::Raw(signal.clone()),
+            signal.id,
+            buses,
+            messages,
+            |data| signal.decode(data),
+        )
+    }
+
+    // Decodes the frames of the id that arrived since the last update, or all of them when the
+    // frames or the buses changed. `decode` gives None for the frames that do not carry the
+    // signal, which are a gap. Returns the version of the series
+    fn update_frames(
+        &mut self,
+        channel: Channel,
+        id: RawCanMessageId,
+        buses: Vec<Bus>,
+        messages: &Messages,
+        decode: impl Fn(&[u8]) -> Option<f64>,
+    ) -> u64 {
+        let frames = messages
+            .frames
+            .get(&id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let cached = self.entries.entry(channel).or_default();
+        if cached.buses != buses
+            || cached.revision != messages.revision
+            || cached.frame_count > frames.len()
+        {
+            self.last_version += 1;
+            *cached = CachedSeries {
+                buses,
+                revision: messages.revision,
+                origin: self.last_version,
+                version: self.last_version,
+                ..Default::default()
+            };
+        }
+        if cached.frame_count == frames.len() {
+            return cached.version;
+        }
+
+        for frame in frames[cached.frame_count..]
+            .iter()
+            .filter(|frame| cached.buses.contains(&frame.bus))
+        {
+            cached
+                .series
+                .push(epoch_seconds(frame.timestamp), decode(&frame.contents));
+        }
+        cached.frame_count = frames.len();
+        self.last_version += 1;
+        cached.version = self.last_version;
+        cached.version
+    }
+
+    // Returns the dbc signals the derived signal is computed from, which are kept in the cache.
+    // The expression is only evaluated on the new points of its signals, and on all of them again
+    // when it or its signals changed otherwise. An invalid expression gives an empty series
+    fn update_derived(
+        &mut self,
+        channel: &Channel,
+        definition: &DerivedSignal,
+        dbcs: &Dbcs,
+        messages: &Messages,
+        hidden_buses: &HashSet<Bus>,
+    ) -> Vec<Signal> {
+        let evaluated = Expr::parse(&definition.expression)
+            .and_then(|expr| {
+                let evaluator = Evaluator::new(&expr)?;
+                let signals = evaluator
+                    .inputs
+                    .iter()
+                    .map(|(message, signal)| dbcs.find_signal(message, signal))
+                    .collect::<Result<Vec<Signal>, String>>()?;
+                Ok((evaluator, signals))
+            })
+            .ok();
+        let signals = evaluated
+            .as_ref()
+            .map(|(_, signals)| signals.clone())
+            .unwrap_or_default();
+        let mut origins = Vec::new();
+        let mut versions = Vec::new();
+        for signal in &signals {
+            versions.push(
+                self.update_signal(signal, dbcs, messages, hidden_buses)
+                    .unwrap_or_default(),
+            );
+            origins.push(
+                self.entries
+                    .get(&Channel::Dbc(signal.clone()))
+                    .map_or(0, |cached| cached.origin),
+            );
+        }
+
+        let mut cached = self.entries.remove(channel).unwrap_or_default();
+        let appending = cached.derived.as_ref().is_some_and(|derived| {
+            derived.expression == definition.expression
+                && derived.signals == signals
+                && derived.origins == origins
+        });
+        if appending
+            && cached
+                .derived
+                .as_ref()
+                .is_some_and(|derived| derived.versions == versions)
+        {
+            self.entries.insert(channel.clone(), cached);
+            return signals;
+        }
+
+        let inputs: Vec<&[[f64; 2]]> = signals
+            .iter()
+            .map(|signal| {
+                self.get(&Channel::Dbc(signal.clone()))
+                    .map(|series| series.points.as_slice())
+                    .unwrap_or_default()
+            })
+            .collect();
+        let appended = cached
+            .derived
+            .as_mut()
+            .filter(|_| appending)
+            .and_then(|derived| derived.evaluator.as_mut())
+            .and_then(|evaluator| evaluator.advance(&inputs));
+        let points = match appended {
+            Some(points) => points,
+            None => {
+                let mut evaluator = evaluated.map(|(evaluator, _)| evaluator);
+                let points = evaluator
+                    .as_mut()
+                    .and_then(|evaluator| evaluator.advance(&inputs))
+                    .unwrap_or_default();
+                cached = CachedSeries {
+                    derived: Some(DerivedState {
+                        expression: definition.expression.clone(),
+                        signals: signals.clone(),
+                        origins,
+                        versions: Vec::new(),
+                        evaluator,
+                    }),
+                    ..Default::default()
+                };
+                self.last_version += 1;
+                cached.origin = self.last_version;
+                points
+            }
+        };
+        for [time, value] in points {
+            cached.series.push(time, value.is_finite().then_some(value));
+        }
+
+        if let Some(derived) = &mut cached.derived {
+            derived.versions = versions;
+        }
+        self.last_version += 1;
+        cached.version = self.last_version;
+        self.entries.insert(channel.clone(), cached);
+        signals
+    }
+
+    // Resamples the new points of the pairs whose series changed, all of them when one of the
+    // series was built again, and drops the rest. After update
+    pub fn update_pairs<'a>(&mut self, pairs: impl Iterator<Item = (&'a Channel, &'a Channel)>) {
+        let mut plotted = HashSet::new();
+        for (x, y) in pairs {
+            let (Some(x_cached), Some(y_cached)) = (self.entries.get(x), self.entries.get(y))
+            else {
+                continue;
+            };
+            let key = (x.clone(), y.clone());
+            let (x_points, y_points) = (&x_cached.series.points, &y_cached.series.points);
+            let origins = [x_cached.origin, y_cached.origin];
+            let versions = [x_cached.version, y_cached.version];
+            let pair = self.pairs.entry(key.clone()).or_default();
+            if pair.versions != versions {
+                let appended = match (x_points.get(pair.read[0]..), y_points.get(pair.read[1]..)) {
+                    (Some(x), Some(y)) if pair.origins == origins => pair.resampler.merge(x, y),
+                    _ => None,
+                };
+                match appended {
+                    Some(points) => pair.points.extend(points),
+                    None => {
+                        let mut resampler = Resampler::default();
+                        *pair = CachedPair {
+                            origins,
+                            points: resampler.merge(x_points, y_points).unwrap_or_default(),
+                            resampler,
+                            ..Default::default()
+                        };
+                    }
+                }
+                pair.versions = versions;
+                pair.read = [x_points.len(), y_points.len()];
+            }
+            plotted.insert(key);
+        }
+
+        self.pairs.retain(|key, _| plotted.contains(key));
+    }
+
+    // Time, x and y values
+    pub fn pair(&self, x: &Channel, y: &Channel) -> Option<&[[f64; 3]]> {
+        self.pairs
+            .get(&(x.clone(), y.clone()))
+            .map(|pair| pair.points.as_slice())
+    }
+
+    // The signals of a dbc that is loaded again may have changed
+    pub fn invalidate_dbc(&mut self, dbc_name: &str) {
+        self.entries.retain(|channel, _| match channel {
+            Channel::Dbc(signal) => &*signal.dbc != dbc_name,
+            Channel::Raw(_) | Channel::Derived(_) => true,
+        });
+    }
+
+    pub fn get(&self, channel: &Channel) -> Option<&Series> {
+        self.entries.get(channel).map(|cached| &cached.series)
+    }
+}
+
+// Statistics of series over a time range, computed again only when the series or the range
+// change instead of on every frame
+#[derive(Debug, Default, Clone)]
+pub struct StatsCache(HashMap<Channel, CachedStats>);
+
+#[derive(Debug, Clone)]
+struct CachedStats {
+    version: u64,
+    range: Option<(f64, f64)>,
+    stats: Option<SeriesStats>,
+}
+
+impl StatsCache {
+    // None if the series is not there or has no values in the range
+    pub fn get(
+        &mut self,
+        series: &SeriesCache,
+        channel: &Channel,
+        range: Option<(f64, f64)>,
+    ) -> Option<SeriesStats> {
+        let cached = series.entries.get(channel)?;
+        if let Some(stats) = self.0.get(channel)
+            && stats.version == cached.version
+            && stats.range == range
+        {
+            return stats.stats;
+        }
+        let stats = cached.series.stats(range);
+        self.0.insert(
+            channel.clone(),
+            CachedStats {
+                version: cached.version,
+                range,
+                stats,
+            },
+        );
+        stats
+    }
+
+    // Forgets the channels that are not shown anymore
+    pub fn retain(&mut self, channels: &[&Channel]) {
+        self.0.retain(|channel, _| channels.contains(&channel));
+    }
+}
+
+// Time and values of both series at every time either of them has a point, each one keeping its
+// last value until its next point. Starts once both have a point. The points can be merged a few
+// at a time
+#[derive(Debug, Default)]
+pub struct Resampler {
+    last: [Option<f64>; 2],
+    // Up to where the points were merged
+    time: Option<f64>,
+}
+
+impl Resampler {
+    // The resampled points for the new points of both series. None when a new point is not after
+    // the ones already merged, the series have to be resampled from the start then
+    pub fn merge(&mut self, a: &[[f64; 2]], b: &[[f64; 2]]) -> Option<Vec<[f64; 3]>> {
+        if let Some(time) = self.time
+            && a.iter().chain(b).any(|point| point[0] <= time)
+        {
+            return None;
+        }
+
+        let mut points = Vec::with_capacity(a.len() + b.len());
+        let (mut a_idx, mut b_idx) = (0, 0);
+        loop {
+            let time = match (a.get(a_idx), b.get(b_idx)) {
+                (Some(a), Some(b)) => a[0].min(b[0]),
+                (Some(a), None) => a[0],
+                (None, Some(b)) => b[0],
+                (None, None) => break,
+            };
+            while let Some(point) = a.get(a_idx).filter(|point| point[0] <= time) {
+                self.last[0] = Some(point[1]);
+                a_idx += 1;
+            }
+            while let Some(point) = b.get(b_idx).filter(|point| point[0] <= time) {
+                self.last[1] = Some(point[1]);
+                b_idx += 1;
+            }
+            self.time = Some(time);
+            if let [Some(a), Some(b)] = self.last {
+                points.push([time, a, b]);
+            }
+        }
+        Some(points)
+    }
+}
+
+pub fn epoch_seconds(timestamp: DateTime<Utc>) -> f64 {
+    timestamp.timestamp() as f64 + timestamp.timestamp_subsec_nanos() as f64 / 1e9
+}
+
+// Decoded values of one signal with a min/max pyramid, so drawing a long log only needs about a
+// point per pixel without losing spikes
+#[derive(Default)]
+pub struct Series {
+    // Seconds since the unix epoch and value, NaN for frames where the signal could not be decoded
+    points: Vec<[f64; 2]>,
+    // The buckets of level k cover LOD_FACTOR^(k+1) points each
+    levels: Vec<Vec<Bucket>>,
+    // Statistics of the whole series, kept up to date as points come in
+    totals: StatsAccumulator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeriesStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    // Seconds since the unix epoch of the first and last values
+    pub first: f64,
+    pub last: f64,
+    // Values per second
+    pub rate: f64,
+    // Standard deviation of the time between values, in seconds
+    pub jitter: f64,
+}
+
+// Mean and standard deviation updated one value at a time with Welford's method, which stays
+// accurate for values with a large offset and a small spread
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RunningStats {
+    pub count: usize,
+    pub mean: f64,
+    // Sum of the squared differences to the mean
+    squares: f64,
+}
+
+impl RunningStats {
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.squares += delta * (value - self.mean);
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        if self.count == 0 {
+            return 0.;
+        }
+        (self.squares / self.count as f64).sqrt()
+    }
+}
+
+// Statistics of values added one point at a time, in time order
+#[derive(Debug, Default, Clone, Copy)]
+struct StatsAccumulator {
+    min: f64,
+    max: f64,
+    values: RunningStats,
+    // Time between consecutive values
+    intervals: RunningStats,
+    // Times of the first and last values
+    first: f64,
+    last: f64,
+}
+
+impl StatsAccumulator {
+    // Points without a value are skipped
+    fn add(&mut self, point: &[f64; 2]) {
+        let [time, value] = *point;
+        if value.is_nan() {
+            return;
+        }
+        if self.values.count == 0 {
+            (self.min, self.max, self.first) = (value, value, time);
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+            self.intervals.add(time - self.last);
+        }
+        self.values.add(value);
+        self.last = time;
+    }
+
+    fn stats(&self) -> Option<SeriesStats> {
+        if self.values.count == 0 {
+            return None;
+        }
+        Some(SeriesStats {
+            count: self.values.count,
+            min: self.min,
+            max: self.max,
+            mean: self.values.mean,
+            std_dev: self.values.std_dev(),
+            first: self.first,
+            last: self.last,
+            rate: if self.intervals.mean > 0. {
+                1. / self.intervals.mean
+            } else {
+                0.
+            },
+            jitter: self.intervals.std_dev(),
+        })
+    }
+}
+
+// Time range of some points with the lowest and highest of them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket {
+    pub start: f64,
+    pub end: f64,
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+}
+
+impl Bucket {
+    fn point(point: &[f64; 2]) -> Self {
+        Self {
+            start: point[0],
+            end: point[0],
+            min: *point,
+            max: *point,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            start: self.start,
+            end: other.end,
+            min: if self.min[1].is_nan() || other.min[1] < self.min[1] {
+                other.min
+            } else {
+                self.min
+            },
+            max: if self.max[1].is_nan() || other.max[1] > self.max[1] {
+                other.max
+            } else {
+                self.max
+            },
+        }
+    }
+
+    // Only made of frames without a value
+    pub fn is_gap(&self) -> bool {
+        self.min[1].is_nan()
+    }
+
+    // The lowest and highest points in time order, once if they are the same
+    pub fn points(&self) -> impl Iterator<Item = [f64; 2]> {
+        let (first, second) = if self.min[0] <= self.max[0] {
+            (self.min, self.max)
+        } else {
+            (self.max, self.min)
+        };
+        std::iter::once(first).chain((first != second).then_some(second))
+    }
+}
+
+impl Series {
+    fn bucket_size(level: usize) -> usize {
+        LOD_FACTOR.pow(level as u32 + 1)
+    }
+
+    // Points have to come in time order
+    pub fn push(&mut self, time: f64, value: Option<f64>) {
+        let point = [time, value.unwrap_or(f64::NAN)];
+        let idx = self.points.len();
+        self.points.push(point);
+        self.totals.add(&point);
+
+        for (level, buckets) in self.levels.iter_mut().enumerate() {
+            let bucket = Bucket::point(&point);
+            match buckets.get_mut(idx / Self::bucket_size(level)) {
+                Some(last) => *last = last.merge(bucket),
+                None => buckets.push(bucket),
+            }
+        }
+
+        // Add a level once the top one has more buckets than it merges
+        while self.points.len() > Self::bucket_size(self.levels.len()) * LOD_FACTOR {
+            let below: Vec<Bucket> = match self.levels.last() {
+                Some(buckets) => buckets.clone(),
+                None => self.points.iter().map(Bucket::point).collect(),
+            };
+            let level = below
+                .chunks(LOD_FACTOR)
+                .map(|chunk| chunk.iter().copied().reduce(Bucket::merge).unwrap())
+                .collect();
+            self.levels.push(level);
+        }
+    }
+
+    // Buckets covering the time range, plus one at each side so lines reach the edges, from the
+    // finest level that needs at most max_buckets of them. The whole series without a range
+    pub fn buckets(&self, range: Option<(f64, f64)>, max_buckets: usize) -> Vec<Bucket> {
+        let (first, last) = match range {
+            Some((min, max)) => (
+                self.points
+                    .partition_point(|point| point[0] < min)
+                    .saturating_sub(1),
+                (self.points.partition_point(|point| point[0] <= max) + 1).min(self.points.len()),
+            ),
+            None => (0, self.points.len()),
+        };
+        let count = last.saturating_sub(first);
+        if count == 0 {
+            return Vec::new();
+        }
+
+        if count > max_buckets {
+            for (level, buckets) in self.levels.iter().enumerate() {
+                let size = Self::bucket_size(level);
+                if count / size <= max_buckets || level + 1 == self.levels.len() {
+                    return buckets[first / size..=(last - 1) / size].to_vec();
+                }
+            }
+        }
+
+        self.points[first..last].iter().map(Bucket::point).collect()
+    }
+
+    // The last value decoded at or before the time
+    pub fn value_at(&self, time: f64) -> Option<f64> {
+        let idx = self
+            .points
+            .partition_point(|point| point[0] <= time)
+            .checked_sub(1)?;
+        let value = self.points[idx][1];
+        (!value.is_nan()).then_some(value)
+    }
+
+    // None if there are no values in the range. The whole series does not have to go over the
+    // points
+    pub fn stats(&self, range: Option<(f64, f64)>) -> Option<SeriesStats> {
+        let Some((start, end)) = range else {
+            return self.totals.stats();
+        };
+        let mut stats = StatsAccumulator::default();
+        for point in self.points_between(start, end) {
+            stats.add(point);
+        }
+        stats.stats()
+    }
+
+    // Points inside the time range, NaN for frames where the signal could not be decoded
+    pub fn points_between(&self, start: f64, end: f64) -> &[[f64; 2]] {
+        let first = self.points.partition_point(|point| point[0] < start);
+        let last = self.points.partition_point(|point| point[0] <= end);
+        &self.points[first..last.max(first)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dbc::Dbc, messages::Message};
+
+    fn series(values: impl IntoIterator<Item = Option<f64>>) -> Series {
+        let mut series = Series::default();
+        for (time, value) in values.into_iter().enumerate() {
+            series.push(time as f64, value);
+        }
+        series
+    }
+
+    #[test]
+    fn small_ranges_are_not_decimated() {
+        let series = series((0..100).map(|value| Some(value as f64)));
+        let buckets = series.buckets(Some((10., 20.)), 50);
+        // One point before and after the range
+        assert_eq!(buckets.len(), 13);
+        assert_eq!(buckets[0], Bucket::point(&[9., 9.]));
+        assert_eq!(buckets[12], Bucket::point(&[21., 21.]));
+    }
+
+    #[test]
+    fn decimation_keeps_spikes() {
+        let mut values = vec![Some(0.); 10_000];
+        values[1234] = Some(50.);
+        values[5678] = Some(-50.);
+        let series = series(values);
+
+        let buckets = series.buckets(None, 100);
+        assert!(buckets.len() <= 100);
+        let points: Vec<[f64; 2]> = buckets.iter().flat_map(Bucket::points).collect();
+        assert!(points.contains(&[1234., 50.]));
+        assert!(points.contains(&[5678., -50.]));
+        assert!(points.is_sorted_by(|a, b| a[0] <= b[0]));
+    }
+
+    #[test]
+    fn levels_match_a_rebuild() {
+        // Built one point at a time, the pyramid must equal merging the points directly
+        let series = series((0..1000).map(|value| Some(((value * 37) % 101) as f64)));
+        for (level, buckets) in series.levels.iter().enumerate() {
+            let size = Series::bucket_size(level);
+            let expected: Vec<Bucket> = series
+                .points
+                .chunks(size)
+                .map(|chunk| {
+                    chunk
+                        .iter()
+                        .map(Bucket::point)
+                        .reduce(Bucket::merge)
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(buckets, &expected);
+        }
+    }
+
+    #[test]
+    fn gaps() {
+        let values = [[Some(1.); 16], [None; 16], [Some(2.); 16]];
+        let series = series(values.into_iter().flatten());
+        // Buckets of 4 points
+        let buckets = series.buckets(None, 12);
+        assert_eq!(buckets.len(), 12);
+        assert!(!buckets[3].is_gap());
+        assert!(buckets[4..8].iter().all(Bucket::is_gap));
+        assert_eq!(buckets[8].min, [32., 2.]);
+    }
+
+    #[test]
+    fn stats() {
+        let mut series = Series::default();
+        for (time, value) in [(0., 2.), (0.1, 4.), (0.2, 4.), (0.35, 4.), (0.4, 5.)] {
+            series.push(time, Some(value));
+        }
+        series.push(0.5, None);
+        series.push(0.6, Some(7.));
+        series.push(0.8, Some(9.));
+
+        let stats = series.stats(Some((0., 0.4))).unwrap();
+        assert_eq!(stats.count, 5);
+        assert_eq!((stats.min, stats.max), (2., 5.));
+        assert!((stats.mean - 3.8).abs() < 1e-9);
+        assert!((stats.std_dev - 0.979_795_897).abs() < 1e-6);
+        assert_eq!((stats.first, stats.last), (0., 0.4));
+        assert!((stats.rate - 10.).abs() < 1e-9);
+        // Intervals of 0.1, 0.1, 0.15 and 0.05
+        assert!((stats.jitter - 0.035_355_339).abs() < 1e-6);
+
+        // Frames without a value are not counted, and the whole series is kept up to date as
+        // points come in
+        assert_eq!(series.stats(None).unwrap().count, 7);
+        assert_eq!(series.stats(None), series.stats(Some((0., 1.))));
+        assert_eq!(series.stats(Some((0.45, 0.55))), None);
+
+        // A large offset does not cancel the spread out
+        let offset = self::series([1e9, 1e9 + 1., 1e9 + 2.].map(Some));
+        let stats = offset.stats(None).unwrap();
+        assert_eq!(stats.mean, 1e9 + 1.);
+        assert!((stats.std_dev - (2f64 / 3.).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pairs_are_resampled_a_few_points_at_a_time() {
+        let x = [[0., 1.], [1., 2.], [2., 3.], [3., 4.]];
+        let y = [[0.5, 10.], [2.5, 30.]];
+        let whole = Resampler::default().merge(&x, &y).unwrap();
+        assert_eq!(
+            whole,
+            [
+                [0.5, 1., 10.],
+                [1., 2., 10.],
+                [2., 3., 10.],
+                [2.5, 3., 30.],
+                [3., 4., 30.]
+            ]
+        );
+
+        let mut resampler = Resampler::default();
+        let mut points = resampler.merge(&x[..2], &y[..1]).unwrap();
+        points.extend(resampler.merge(&x[2..], &y[1..]).unwrap());
+        assert_eq!(points, whole);
+        // Points that were already merged can not come again
+        assert_eq!(resampler.merge(&x[3..], &[]), None);
+    }
+
+    #[test]
+    fn other_multiplexor_values_are_gaps() {
+        let dbc = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\n\
+            BO_ 256 Status: 2 ECU\n \
+            SG_ Page M : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX\n \
+            SG_ Level m1 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX\n";
+        let mut dbcs = Dbcs::default();
+        dbcs.insert(Dbc::new(Arc::from("status.dbc"), Arc::from(dbc.as_bytes())).unwrap());
+        let messages = Messages::from_string(
+            "(1.000000) can0 100#0105\n(2.000000) can0 100#0207\n(3.000000) can0 100#0109".into(),
+        );
+        let channel = Channel::Dbc(dbcs.find_signal("Status", "Level").unwrap());
+
+        let mut cache = SeriesCache::default();
+        cache.update(
+            [&channel].into_iter(),
+            &[],
+            &dbcs,
+            &messages,
+            &HashSet::new(),
+        );
+        let points = &cache.get(&channel).unwrap().points;
+        assert_eq!(points.len(), 3);
+        assert_eq!((points[0], points[2]), ([1., 5.], [3., 9.]));
+        assert!(points[1][1].is_nan());
+    }
+
+    #[test]
+    fn derived_signals_follow_new_frames() {
+        let dbc = "VERSION \"\"\n\nNS_ :\n\nBS_:\n\nBU_: ECU\n\n\
+            BO_ 256 Power: 2 ECU\n \
+            SG_ Voltage : 0|8@1+ (1,0) [0|255] \"V\" Vector__XXX\n \
+            SG_ Current : 8|8@1+ (1,0) [0|255] \"A\" Vector__XXX\n";
+        let mut dbcs = Dbcs::default();
+        dbcs.insert(Dbc::new(Arc::from("power.dbc"), Arc::from(dbc.as_bytes())).unwrap());
+        let mut messages = Messages::from_string("(1.000000) can0 100#0A02".into());
+        let derived = [DerivedSignal {
+            name: Arc::from("Power"),
+            unit: String::from("W"),
+            expression: String::from("Power.Voltage * Power.Current"),
+        }];
+        let channel = Channel::Derived(Arc::from("Power"));
+
+        let mut cache = SeriesCache::default();
+        let update = |cache: &mut SeriesCache, messages: &Messages| {
+            cache.update(
+                [&channel].into_iter(),
+                &derived,
+                &dbcs,
+                messages,
+                &HashSet::new(),
+            );
+        };
+        update(&mut cache, &messages);
+        assert_eq!(cache.get(&channel).unwrap().points, [[1., 20.]]);
+        let origin = cache.entries[&channel].origin;
+
+        let (id, frame) = Message::from_str("(2.000000) can0 100#0C03").unwrap();
+        messages.push(id, frame);
+        update(&mut cache, &messages);
+        assert_eq!(cache.get(&channel).unwrap().points, [[1., 20.], [2., 36.]]);
+        // Only the new frame was evaluated
+        assert_eq!(cache.entries[&channel].origin, origin);
+        // The inputs stay cached
+        let voltage = dbcs.find_signal("Power", "Voltage").unwrap();
+        assert!(cache.get(&Channel::Dbc(voltage)).is_some());
+    }
+}
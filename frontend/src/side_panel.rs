@@ -1,12 +1,17 @@
-use egui::{Id, TextEdit};
+use egui::{Id, RichText, TextEdit, Ui};
 use futures::StreamExt;
 use gloo_net::websocket::Message;
 use num_format::{Locale, ToFormattedString};
 use rfd::AsyncFileDialog;
-use std::{cell::RefCell, rc::Rc, sync::Arc};
+use std::{cell::RefCell, collections::BTreeSet, rc::Rc, sync::Arc};
 use wasm_bindgen_futures::spawn_local;
 
-use crate::{App, dbc::Signal, messages::Messages};
+use crate::{
+    App,
+    dbc::Signal,
+    messages::{Bus, Messages},
+    series::Channel,
+};
 
 impl App {
     pub fn draw_side_panel(&mut self, ctx: &egui::Context, app_handle: Rc<RefCell<App>>) {
@@ -17,7 +22,7 @@ impl App {
                 ui.horizontal(|ui| {
                     ui.heading("Messages:");
                     if ui.button("Clear").clicked() {
-                        self.messages.0.clear();
+                        self.messages.clear();
                     }
                     if ui.button("Add from log file").clicked() {
                         let app_handle = app_handle.clone();
@@ -97,69 +102,181 @@ impl App {
                     ui.label("Ammount: ");
                     ui.label(self.messages.len().to_formatted_string(&Locale::en));
                 });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Buses: ");
+                    for bus in &self.messages.buses {
+                        let mut visible = !self.hidden_buses.contains(bus);
+                        if ui.checkbox(&mut visible, bus.as_str()).changed() {
+                            if visible {
+                                self.hidden_buses.remove(bus);
+                            } else {
+                                self.hidden_buses.insert(*bus);
+                            }
+                        }
+                    }
+                });
                 ui.separator();
 
                 // Dbc File selector
-                ui.heading("DBC:");
-                let mut should_remove_dbc = false;
-                if let Some(dbc) = &self.dbc {
+                ui.horizontal(|ui| {
+                    ui.heading("DBCs:");
+                    let ctx = ctx.clone();
+                    if ui
+                        .button("Select DBC")
+                        .on_hover_text("KCD, SYM and ARXML files are converted to dbc")
+                        .clicked()
+                    {
+                        spawn_local(async move {
+                            if let Some(file) = AsyncFileDialog::new()
+                                .add_filter(
+                                    "Network descriptions",
+                                    &["dbc", "DBC", "kcd", "KCD", "sym", "SYM", "arxml", "ARXML"],
+                                )
+                                .set_directory("/")
+                                .pick_file()
+                                .await
+                            {
+                                // Not borrowed while reading, the app is drawn meanwhile
+                                let bytes = file.read().await;
+                                app_handle
+                                    .borrow_mut()
+                                    .handle_network_file(file.file_name(), Arc::from(bytes));
+                                ctx.request_repaint();
+                            }
+                        });
+                    }
+                });
+                if self.dbcs.0.is_empty() {
+                    ui.label("No dbc loaded");
+                }
+
+                let mut dbc_to_remove = None;
+                let mut dbc_to_raise = None;
+                let dbc_count = self.dbcs.0.len();
+                for (dbc_idx, dbc) in self.dbcs.0.iter_mut().enumerate() {
                     ui.horizontal(|ui| {
                         ui.label(&*dbc.name);
-                        should_remove_dbc = ui.button("Remove").clicked();
-                    });
-                } else {
-                    ui.horizontal(|ui| {
-                        ui.label("No dbc loaded");
-                        let ctx = ctx.clone();
-                        if ui.button("Select DBC").clicked() {
-                            spawn_local(async move {
-                                if let Some(file) = AsyncFileDialog::new()
-                                    .add_filter("DBC Files", &["dbc", "DBC"])
-                                    .set_directory("/")
-                                    .pick_file()
-                                    .await
-                                {
-                                    app_handle
-                                        .borrow_mut()
-                                        .handle_dbc(file.file_name(), Arc::from(file.read().await));
-                                    ctx.request_repaint();
-                                }
-                            });
+                        if let Some(warning) = &dbc.warning {
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                                .on_hover_text(warning);
+                        }
+                        bus_selector_ui(ui, &dbc.name, &mut dbc.bus, &self.messages.buses);
+                        // Order is priority when two dbcs define the same message
+                        if ui
+                            .add_enabled(dbc_idx > 0, egui::Button::new("⬆"))
+                            .clicked()
+                        {
+                            dbc_to_raise = Some(dbc_idx);
+                        }
+                        if ui
+                            .add_enabled(dbc_idx + 1 < dbc_count, egui::Button::new("⬇"))
+                            .clicked()
+                        {
+                            dbc_to_raise = Some(dbc_idx + 1);
+                        }
+                        if ui.button("Remove").clicked() {
+                            dbc_to_remove = Some(dbc_idx);
                         }
                     });
                 }
-                if should_remove_dbc {
-                    let _ = self.dbc.take();
+                if let Some(dbc_idx) = dbc_to_raise {
+                    self.dbcs.0.swap(dbc_idx - 1, dbc_idx);
+                }
+                if let Some(dbc_idx) = dbc_to_remove {
+                    self.dbcs.0.remove(dbc_idx);
                 }
 
-                // DBC Message viewer
-                let Some(dbc) = &self.dbc else {
-                    ui.heading("No DBC file loaded");
-                    return;
-                };
+                let conflicts = self.dbcs.conflicts();
+                for conflict in &conflicts {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "⚠ {} is defined in {} and {}, using {}",
+                            conflict.message_id, conflict.used, conflict.shadowed, conflict.used
+                        ),
+                    );
+                }
 
-                ui.heading("DBC Messages");
+                // Signals to drag into the plots
+                ui.separator();
+                ui.heading("Signals");
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    for message in dbc.inner.messages() {
-                        egui::collapsing_header::CollapsingHeader::new(message.message_name())
+                    for dbc in &self.dbcs.0 {
+                        egui::collapsing_header::CollapsingHeader::new(&*dbc.name)
+                            .default_open(true)
                             .show(ui, |ui| {
-                                message.signals().iter().enumerate().for_each(
-                                    |(signal_idx, signal)| {
-                                        ui.dnd_drag_source(
-                                            Id::new(message.message_id().raw()).with(signal_idx),
-                                            Signal {
-                                                message_id: (*message.message_id()).into(),
-                                                signal_idx,
-                                            },
-                                            |ui| {
-                                                ui.label(signal.name());
-                                            },
-                                        );
-                                    },
-                                );
+                                for message in dbc.inner.messages() {
+                                    let message_id = (*message.message_id()).into();
+                                    let shadowed = conflicts.iter().any(|conflict| {
+                                        conflict.message_id == message_id
+                                            && conflict.shadowed == dbc.name
+                                    });
+                                    let header = if shadowed {
+                                        RichText::new(format!("⚠ {}", message.message_name()))
+                                            .color(ui.visuals().warn_fg_color)
+                                    } else {
+                                        RichText::new(message.message_name())
+                                    };
+
+                                    egui::collapsing_header::CollapsingHeader::new(header)
+                                        .id_salt((&*dbc.name, message.message_id().raw()))
+                                        .show(ui, |ui| {
+                                            message.signals().iter().enumerate().for_each(
+                                                |(signal_idx, signal)| {
+                                                    ui.dnd_drag_source(
+                                                        Id::new((
+                                                            &*dbc.name,
+                                                            message.message_id().raw(),
+                                                            signal_idx,
+                                                        )),
+                                                        Channel::Dbc(Signal {
+                                                            dbc: dbc.name.clone(),
+                                                            message_id,
+                                                            signal_idx,
+                                                        }),
+                                                        |ui| {
+                                                            ui.label(signal.name());
+                                                        },
+                                                    );
+                                                },
+                                            );
+                                        });
+                                }
                             });
                     }
+
+                    egui::collapsing_header::CollapsingHeader::new("Derived signals")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            self.plots.derived_signals_ui(ui, &self.dbcs);
+                        });
+
+                    // Bits of any id, for frames no dbc describes
+                    egui::collapsing_header::CollapsingHeader::new("Raw signals").show(ui, |ui| {
+                        self.raw_signal.builder_ui(ui, &self.messages);
+                    });
                 });
             });
     }
 }
+
+fn bus_selector_ui(ui: &mut Ui, id_salt: &str, selected: &mut Option<Bus>, buses: &BTreeSet<Bus>) {
+    let current = *selected;
+    let selected_text = match current {
+        Some(bus) => bus.to_string(),
+        None => "All buses".to_string(),
+    };
+    egui::ComboBox::from_id_salt(id_salt)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(selected, None, "All buses");
+            // Keep the current one even if there are no frames of it loaded yet
+            for bus in buses
+                .iter()
+                .copied()
+                .chain(current.filter(|bus| !buses.contains(bus)))
+            {
+                ui.selectable_value(selected, Some(bus), bus.as_str());
+            }
+        });
+}
@@ -0,0 +1,358 @@
+use chrono::{DateTime, Local, Utc};
+use egui::{Label, Layout, RichText, TextEdit, Ui, WidgetText};
+use num_format::{Locale, ToFormattedString};
+use std::collections::HashMap;
+
+use crate::{
+    dbc::Dbcs,
+    messages::{Messages, RawCanMessageId},
+    plots::Plots,
+};
+
+// Width of every column but the data, which takes the rest
+const COLUMN_WIDTHS: [(&str, f32); 6] = [
+    ("", 30.),
+    ("Time", 190.),
+    ("Bus", 60.),
+    ("ID", 90.),
+    ("DLC", 35.),
+    ("Message", 150.),
+];
+
+// Every frame in time order, like candump prints them
+#[derive(Default)]
+pub struct Trace {
+    pub open: bool,
+    time_mode: TraceTime,
+    id_filter: String,
+    // Hide the ids of the filter instead of showing only them
+    exclude: bool,
+
+    // Id and position in Messages::frames of every frame, in time order
+    index: Vec<(RawCanMessageId, u32)>,
+    // What the index was built from
+    revision: u64,
+    indexed: HashMap<RawCanMessageId, usize>,
+    // Positions in the index of the frames that pass the filter
+    filtered: Vec<u32>,
+    filtered_len: usize,
+    applied_filter: (Vec<RawCanMessageId>, bool),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum TraceTime {
+    // Seconds since the first frame of the log
+    #[default]
+    Fixed,
+    Absolute,
+    // Seconds since the row above
+    Delta,
+}
+
+impl Trace {
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        messages: &Messages,
+        dbcs: &Dbcs,
+        plots: &mut Plots,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Trace")
+            .open(&mut open)
+            .default_size([900., 500.])
+            .show(ctx, |ui| self.ui(ui, messages, dbcs, plots));
+        self.open = open;
+    }
+
+    fn ui(&mut self, ui: &mut Ui, messages: &Messages, dbcs: &Dbcs, plots: &mut Plots) {
+        self.update_index(messages);
+
+        ui.horizontal(|ui| {
+            ui.label("Time:");
+            ui.selectable_value(&mut self.time_mode, TraceTime::Fixed, "Fixed")
+                .on_hover_text("Seconds since the first frame");
+            ui.selectable_value(&mut self.time_mode, TraceTime::Absolute, "Absolute");
+            ui.selectable_value(&mut self.time_mode, TraceTime::Delta, "Delta")
+                .on_hover_text("Seconds since the frame above");
+            ui.separator();
+            ui.label("IDs:");
+            ui.add(
+                TextEdit::singleline(&mut self.id_filter)
+                    .hint_text("100, 18FF0001")
+                    .desired_width(150.),
+            );
+            ui.checkbox(&mut self.exclude, "Exclude");
+        });
+        let ids = match parse_ids(&self.id_filter) {
+            Ok(ids) => ids,
+            Err(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                Vec::new()
+            }
+        };
+        self.update_filter(ids);
+
+        let row_count = self.row_count();
+        ui.label(format!(
+            "{} frames",
+            row_count.to_formatted_string(&Locale::en)
+        ));
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            for (name, width) in COLUMN_WIDTHS {
+                cell(ui, width, RichText::new(name).strong());
+            }
+            ui.strong("Data");
+        });
+
+        let first_timestamp = messages.first_timestamp().unwrap_or_default();
+        let timestamp_at = |row: usize| {
+            let (id, idx) = self.row(row);
+            messages.frames[&id][idx as usize].timestamp
+        };
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::both().auto_shrink(false).show_rows(
+            ui,
+            row_height,
+            row_count,
+            |ui, rows| {
+                for row in rows {
+                    let (id, idx) = self.row(row);
+                    let frame = &messages.frames[&id][idx as usize];
+                    let time = match self.time_mode {
+                        TraceTime::Fixed => seconds_label(frame.timestamp - first_timestamp),
+                        TraceTime::Absolute => DateTime::<Local>::from(frame.timestamp)
+                            .format("%Y-%m-%d %H:%M:%S%.6f")
+                            .to_string(),
+                        TraceTime::Delta => match row.checked_sub(1) {
+                            Some(previous) => {
+                                seconds_label(frame.timestamp - timestamp_at(previous))
+                            }
+                            None => seconds_label(chrono::TimeDelta::zero()),
+                        },
+                    };
+                    let message_name = dbcs
+                        .message_owner(id, frame.bus)
+                        .and_then(|dbc| dbc.messages_map.get(&id))
+                        .map(|message| message.message_name().as_str())
+                        .unwrap_or_default();
+                    let mut data = String::with_capacity(frame.contents.len() * 3);
+                    for byte in &frame.contents {
+                        data.push_str(&format!("{byte:02X} "));
+                    }
+
+                    ui.horizontal(|ui| {
+                        let [jump, time_width, bus, id_width, dlc, message] =
+                            COLUMN_WIDTHS.map(|(_, width)| width);
+                        ui.allocate_ui_with_layout(
+                            egui::vec2(jump, row_height),
+                            Layout::left_to_right(egui::Align::Center),
+                            |ui| {
+                                if ui
+                                    .small_button("▶")
+                                    .on_hover_text("Show this time in the plots")
+                                    .clicked()
+                                {
+                                    plots.jump_to(frame.timestamp);
+                                }
+                            },
+                        );
+                        cell(ui, time_width, RichText::new(time).monospace());
+                        cell(ui, bus, frame.bus.as_str());
+                        cell(ui, id_width, RichText::new(id.to_string()).monospace());
+                        cell(ui, dlc, frame.dlc.to_string());
+                        cell(ui, message, message_name);
+                        ui.monospace(data);
+                    });
+                }
+            },
+        );
+    }
+
+    // Adds the new frames to the index, or builds it again if they do not go after the indexed
+    // ones
+    fn update_index(&mut self, messages: &Messages) {
+        if self.revision != messages.revision {
+            self.reset(messages.revision);
+        }
+
+        let mut new_frames = self.new_frames(messages);
+        let Some((first_new, ..)) = new_frames.first() else {
+            return;
+        };
+        let last_indexed = self
+            .index
+            .last()
+            .map(|(id, idx)| messages.frames[id][*idx as usize].timestamp);
+        if last_indexed.is_some_and(|last_indexed| *first_new < last_indexed) {
+            self.reset(messages.revision);
+            new_frames = self.new_frames(messages);
+        }
+
+        self.index
+            .extend(new_frames.into_iter().map(|(_, id, idx)| (id, idx)));
+    }
+
+    fn new_frames(&mut self, messages: &Messages) -> Vec<(DateTime<Utc>, RawCanMessageId, u32)> {
+        let mut new_frames = Vec::new();
+        for (id, frames) in &messages.frames {
+            let indexed = self.indexed.entry(*id).or_default();
+            new_frames.extend(
+                frames
+                    .get(*indexed..)
+                    .unwrap_or_default()
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, frame)| (frame.timestamp, *id, (*indexed + offset) as u32)),
+            );
+            *indexed = frames.len();
+        }
+        new_frames.sort_by_key(|(timestamp, ..)| *timestamp);
+        new_frames
+    }
+
+    fn reset(&mut self, revision: u64) {
+        self.index.clear();
+        self.indexed.clear();
+        self.revision = revision;
+        self.filtered.clear();
+        self.filtered_len = 0;
+    }
+
+    fn update_filter(&mut self, ids: Vec<RawCanMessageId>) {
+        let filter = (ids, self.exclude);
+        if filter != self.applied_filter {
+            self.filtered.clear();
+            self.filtered_len = 0;
+            self.applied_filter = filter;
+        }
+
+        let (ids, exclude) = &self.applied_filter;
+        if ids.is_empty() {
+            return;
+        }
+        for (position, (id, _)) in self.index.iter().enumerate().skip(self.filtered_len) {
+            if ids.contains(id) != *exclude {
+                self.filtered.push(position as u32);
+            }
+        }
+        self.filtered_len = self.index.len();
+    }
+
+    fn row_count(&self) -> usize {
+        if self.applied_filter.0.is_empty() {
+            self.index.len()
+        } else {
+            self.filtered.len()
+        }
+    }
+
+    fn row(&self, row: usize) -> (RawCanMessageId, u32) {
+        if self.applied_filter.0.is_empty() {
+            self.index[row]
+        } else {
+            self.index[self.filtered[row] as usize]
+        }
+    }
+}
+
+// Hex ids separated by commas or spaces, extended when written with 8 digits like candump does
+fn parse_ids(filter: &str) -> Result<Vec<RawCanMessageId>, String> {
+    filter
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            let digits = id.trim_start_matches("0x").trim_start_matches("0X");
+            RawCanMessageId::from_hex(digits).ok_or_else(|| format!("{id} is not a hex id"))
+        })
+        .collect()
+}
+
+fn seconds_label(delta: chrono::TimeDelta) -> String {
+    format!(
+        "{:.6}",
+        delta.num_seconds() as f64 + delta.subsec_nanos() as f64 / 1e9
+    )
+}
+
+fn cell(ui: &mut Ui, width: f32, text: impl Into<WidgetText>) {
+    ui.allocate_ui_with_layout(
+        egui::vec2(width, ui.spacing().interact_size.y),
+        Layout::left_to_right(egui::Align::Center),
+        |ui| {
+            ui.set_width(width);
+            ui.add(Label::new(text).truncate());
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Message;
+
+    fn times(trace: &Trace, messages: &Messages) -> Vec<String> {
+        (0..trace.row_count())
+            .map(|row| {
+                let (id, idx) = trace.row(row);
+                seconds_label(
+                    messages.frames[&id][idx as usize].timestamp
+                        - messages.first_timestamp().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frames_in_time_order() {
+        let mut messages =
+            Messages::from_string("(1.0) can0 100#01\n(1.5) can0 200#02\n(2.0) can0 100#03".into());
+        let mut trace = Trace::default();
+        trace.update_index(&messages);
+        assert_eq!(
+            times(&trace, &messages),
+            ["0.000000", "0.500000", "1.000000"]
+        );
+
+        // Appended after the indexed frames
+        let (id, frame) = Message::from_str("(3.0) can0 200#04").unwrap();
+        messages.push(id, frame);
+        trace.update_index(&messages);
+        assert_eq!(trace.index.len(), 4);
+
+        // Before the last indexed frame but at the end of its id
+        let (id, frame) = Message::from_str("(2.5) can0 300#05").unwrap();
+        messages.push(id, frame);
+        trace.update_index(&messages);
+        assert_eq!(
+            times(&trace, &messages),
+            ["0.000000", "0.500000", "1.000000", "1.500000", "2.000000"]
+        );
+
+        trace.update_filter(parse_ids("200, 0x300").unwrap());
+        assert_eq!(
+            times(&trace, &messages),
+            ["0.500000", "1.500000", "2.000000"]
+        );
+        trace.exclude = true;
+        trace.update_filter(parse_ids("200 300").unwrap());
+        assert_eq!(times(&trace, &messages), ["0.000000", "1.000000"]);
+        assert!(parse_ids("100, xyz").is_err());
+    }
+
+    #[test]
+    fn standard_and_extended_ids_are_filtered_apart() {
+        let messages = Messages::from_string(
+            "(1.0) can0 100#01\n(2.0) can0 00000100#02\n(3.0) can0 12345#03".into(),
+        );
+        let mut trace = Trace::default();
+        trace.update_index(&messages);
+        trace.update_filter(parse_ids("100").unwrap());
+        assert_eq!(times(&trace, &messages), ["0.000000"]);
+        trace.update_filter(parse_ids("0x00000100, 12345").unwrap());
+        assert_eq!(times(&trace, &messages), ["1.000000", "2.000000"]);
+        assert!(parse_ids("20000000").is_err());
+    }
+}
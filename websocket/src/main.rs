@@ -1,10 +1,10 @@
 use futures_util::SinkExt;
-use socketcan::{EmbeddedFrame, Frame, tokio::CanSocket};
+use socketcan::{CanAnyFrame, EmbeddedFrame, Frame, tokio::CanFdSocket};
 use std::fmt;
 use std::sync::Arc;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::broadcast::{Receiver, channel};
+use tokio::sync::broadcast::{Receiver, Sender, channel};
 use tokio_tungstenite::tungstenite::Message;
 
 struct Hex<'a>(&'a [u8]);
@@ -34,6 +34,51 @@ async fn handle_conn(stream: TcpStream, mut msg_recv: Receiver<Arc<str>>) {
     }
 }
 
+async fn read_can(
+    ifname: String,
+    can_socket: CanFdSocket,
+    start_time: SystemTime,
+    time: Instant,
+    message_sender: Sender<Arc<str>>,
+) {
+    loop {
+        let Ok(frame) = can_socket.read_frame().await else {
+            continue;
+        };
+
+        // candump tells extended ids apart by always writing them with 8 digits
+        let id = if frame.is_extended() {
+            format!("{:08X}", frame.raw_id())
+        } else {
+            format!("{:03X}", frame.raw_id())
+        };
+        let payload = match &frame {
+            CanAnyFrame::Normal(frame) => format!("#{:X}", Hex(frame.data())),
+            // candump FD syntax: ID##<flags><data>, BRS is bit 0 and ESI bit 1
+            CanAnyFrame::Fd(frame) => format!(
+                "##{:X}{:X}",
+                frame.is_brs() as u8 | (frame.is_esi() as u8) << 1,
+                Hex(frame.data())
+            ),
+            // Nothing to plot on those
+            CanAnyFrame::Remote(_) | CanAnyFrame::Error(_) => continue,
+        };
+
+        let timestamp = start_time + time.elapsed();
+        // UNSAFE: Time goes forward
+        let timestamp = unsafe { timestamp.duration_since(UNIX_EPOCH).unwrap_unchecked() };
+
+        let _ = message_sender.send(Arc::from(format!(
+            "({}.{:09}) {} {}{}",
+            timestamp.as_secs(),
+            timestamp.subsec_nanos(),
+            ifname,
+            id,
+            payload
+        )));
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> std::io::Result<()> {
     let _ = dotenvy::dotenv().map_err(|e| {
@@ -43,8 +88,15 @@ async fn main() -> std::io::Result<()> {
         e
     });
 
-    let can_socket =
-        CanSocket::open(&std::env::var("CAN_SOCKET").expect("CAN_SOCKET env var must be set"))?;
+    // Comma separated list of interfaces, e.g. "can0,can1"
+    let can_sockets = std::env::var("CAN_SOCKET")
+        .expect("CAN_SOCKET env var must be set")
+        .split(',')
+        .map(|ifname| {
+            let ifname = ifname.trim();
+            CanFdSocket::open(ifname).map(|can_socket| (ifname.to_string(), can_socket))
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
     let tcp_listener =
         TcpListener::bind(std::env::var("HOST_ADDR").expect("HOST_ADDR env var must be set"))
             .await?;
@@ -54,35 +106,23 @@ async fn main() -> std::io::Result<()> {
 
     let (message_sender, message_recv) = channel(128);
 
-    // Accept task
-    tokio::spawn(async move {
-        loop {
-            let Ok((tcp_stream, _addr)) = tcp_listener.accept().await else {
-                continue;
-            };
-
-            tokio::spawn(handle_conn(tcp_stream, message_recv.resubscribe()));
-        }
-    });
+    // Read tasks, one per interface
+    for (ifname, can_socket) in can_sockets {
+        tokio::spawn(read_can(
+            ifname,
+            can_socket,
+            start_time,
+            time,
+            message_sender.clone(),
+        ));
+    }
 
+    // Accept loop
     loop {
-        let Ok(frame) = can_socket.read_frame().await else {
+        let Ok((tcp_stream, _addr)) = tcp_listener.accept().await else {
             continue;
         };
 
-        let id = frame.raw_id();
-        let data = frame.data();
-
-        let timestamp = start_time + time.elapsed();
-        // UNSAFE: Time goes forward
-        let timestamp = unsafe { timestamp.duration_since(UNIX_EPOCH).unwrap_unchecked() };
-
-        let _ = message_sender.send(Arc::from(format!(
-            "({}.{:09}) can0 {:X}#{:X}",
-            timestamp.as_secs(),
-            timestamp.subsec_nanos(),
-            id,
-            Hex(data)
-        )));
+        tokio::spawn(handle_conn(tcp_stream, message_recv.resubscribe()));
     }
 }